@@ -39,7 +39,12 @@ fn test_cells(c: &mut Criterion, rounds: usize, cells: usize) {
             });
 
             (0..black_box(rounds)).for_each(|_| {
-                update_cells(&mut world.cells);
+                update_cells(
+                    &mut world.cells,
+                    &world.cell_config,
+                    world.scheduler.chemistry_steps,
+                    world.scheduler.chemistry_dt,
+                );
             })
         })
     });
@@ -1,8 +1,85 @@
-#[derive(Debug, Clone, Copy, Default)]
+use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Chemicals {
     pub atp: f32,
     pub glucose: f32,
+    /// Metabolic byproducts, toxic above `CellConfig::waste_tolerance`.
+    pub waste: f32,
+    /// Signalling molecule secreted by cells and picked up by receptors.
+    pub signal: f32,
 }
 
 pub const ATP_SIZE: f32 = 1.;
 pub const GLUCOSE_SIZE: f32 = 10.;
+pub const WASTE_SIZE: f32 = 1.;
+pub const SIGNAL_SIZE: f32 = 0.1;
+
+impl Chemicals {
+    pub fn size(&self) -> f32 {
+        self.atp * ATP_SIZE + self.glucose * GLUCOSE_SIZE + self.waste * WASTE_SIZE
+            + self.signal * SIGNAL_SIZE
+    }
+
+    pub fn solutes(&self) -> f32 {
+        self.atp + self.glucose + self.waste + self.signal
+    }
+
+    pub fn clamp_non_negative(&mut self) {
+        self.atp = self.atp.max(0.);
+        self.glucose = self.glucose.max(0.);
+        self.waste = self.waste.max(0.);
+        self.signal = self.signal.max(0.);
+    }
+}
+
+impl Add for Chemicals {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            atp: self.atp + rhs.atp,
+            glucose: self.glucose + rhs.glucose,
+            waste: self.waste + rhs.waste,
+            signal: self.signal + rhs.signal,
+        }
+    }
+}
+
+impl Sub for Chemicals {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            atp: self.atp - rhs.atp,
+            glucose: self.glucose - rhs.glucose,
+            waste: self.waste - rhs.waste,
+            signal: self.signal - rhs.signal,
+        }
+    }
+}
+
+impl Mul<f32> for Chemicals {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        Self {
+            atp: self.atp * rhs,
+            glucose: self.glucose * rhs,
+            waste: self.waste * rhs,
+            signal: self.signal * rhs,
+        }
+    }
+}
+
+impl AddAssign for Chemicals {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Chemicals {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
@@ -0,0 +1,15 @@
+use crate::cell::chemicals::ATP_SIZE;
+use crate::cell::Cell;
+
+use super::{Amounts, ATP_HYDROLYSIS_PH_SHIFT};
+
+/// Fraction of surface adhesins shed per unit of time.
+const ADHESIN_TURNOVER: f32 = 1.;
+
+pub fn adhesion(cell: &mut Cell, amount: &Amounts) {
+    cell.inner.chemicals.atp -= amount.input;
+    cell.adhesion += amount.output - cell.adhesion * ADHESIN_TURNOVER * amount.dt;
+
+    cell.modify_size(-amount.input * ATP_SIZE);
+    cell.modify_ph(amount.input * ATP_HYDROLYSIS_PH_SHIFT);
+}
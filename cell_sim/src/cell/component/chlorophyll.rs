@@ -1,12 +1,13 @@
-use rapier2d::prelude::*;
-
 use crate::cell::chemicals::GLUCOSE_SIZE;
 use crate::cell::Cell;
 
-use super::ComponentProps;
+use super::Amounts;
+
+/// Fixing carbon dioxide removes carbonic acid.
+const PH_SHIFT: f32 = 0.05;
 
-pub fn chlorophyll(props: &ComponentProps, cell: &mut Cell) {
-    let amount = props.get_input_output_amt(0.);
+pub fn chlorophyll(cell: &mut Cell, amount: &Amounts) {
     cell.inner.chemicals.glucose += amount.output;
     cell.modify_size(amount.output * GLUCOSE_SIZE);
+    cell.modify_ph(amount.output * PH_SHIFT);
 }
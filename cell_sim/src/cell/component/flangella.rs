@@ -3,12 +3,12 @@ use rapier2d::prelude::*;
 use crate::cell::chemicals::ATP_SIZE;
 use crate::cell::Cell;
 
-use super::ComponentProps;
+use super::{Amounts, ATP_HYDROLYSIS_PH_SHIFT};
 
-pub fn flangella(props: &ComponentProps, cell: &mut Cell) {
-    let amount = props.get_input_output_amt(cell.inner.chemicals.atp);
+pub fn flangella(cell: &mut Cell, amount: &Amounts) {
     cell.inner.chemicals.atp -= amount.input;
     cell.modify_size(-amount.input * ATP_SIZE);
+    cell.modify_ph(amount.input * ATP_HYDROLYSIS_PH_SHIFT);
 
     let (leftneg, rightneg) = (rand::random::<bool>(), rand::random::<bool>());
     let mut left = rand::random::<f32>();
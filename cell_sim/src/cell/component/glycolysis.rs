@@ -1,14 +1,20 @@
-use rapier2d::prelude::*;
-
-use crate::cell::chemicals::{ATP_SIZE, GLUCOSE_SIZE};
+use crate::cell::chemicals::{ATP_SIZE, GLUCOSE_SIZE, WASTE_SIZE};
 use crate::cell::Cell;
 
-use super::ComponentProps;
+use super::Amounts;
+
+/// Lactate acidifies the cell.
+const PH_SHIFT: f32 = -0.05;
+/// Waste produced per glucose consumed.
+const WASTE_YIELD: f32 = 0.5;
 
-pub fn glycolysis(props: &ComponentProps, cell: &mut Cell) {
-    let amount = props.get_input_output_amt(cell.inner.chemicals.glucose);
+pub fn glycolysis(cell: &mut Cell, amount: &Amounts) {
     cell.inner.chemicals.glucose -= amount.input;
     cell.inner.chemicals.atp += amount.output;
+    cell.inner.chemicals.waste += amount.input * WASTE_YIELD;
 
-    cell.modify_size(amount.output * GLUCOSE_SIZE - amount.input * ATP_SIZE);
+    cell.modify_size(
+        amount.output * ATP_SIZE + amount.input * (WASTE_YIELD * WASTE_SIZE - GLUCOSE_SIZE),
+    );
+    cell.modify_ph(amount.output * PH_SHIFT);
 }
@@ -1,31 +1,85 @@
+mod adhesion;
 mod chlorophyll;
 mod flangella;
 mod glycolysis;
 mod nucleotide_de_novo;
 mod protein_de_novo;
-use rapier2d::dynamics::RigidBody;
-use rapier2d::geometry::Collider;
+mod receptor;
+mod secretion;
 
+use rand::Rng;
+
+use crate::cell::config::CellConfig;
 use crate::cell::Cell;
 use crate::STEP_SIZE;
 
-use super::inner::PROTEIN_SIZE;
+use super::inner::{NEUTRAL_PH, PROTEIN_SIZE};
+
+/// Applies what a component consumed and produced over one integration
+/// step to the cell.
+pub type Reaction = fn(&mut Cell, &Amounts);
 
-pub type Component = fn(&ComponentProps, &mut Cell);
+/// Pool a component draws its input from. Variants are declared in the
+/// same order as `Substrate::ALL` so they can index per-substrate arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Substrate {
+    Atp,
+    Glucose,
+    /// Light reaching the cell, renewed continuously rather than stored.
+    Light,
+}
+
+impl Substrate {
+    pub const ALL: [Substrate; 3] = [Substrate::Atp, Substrate::Glucose, Substrate::Light];
+
+    /// Amount of the substrate the cell can spend over `dt`.
+    pub fn available(&self, cell: &Cell, dt: f32) -> f32 {
+        match self {
+            Substrate::Atp => cell.inner.chemicals.atp.max(0.),
+            Substrate::Glucose => cell.inner.chemicals.glucose.max(0.),
+            Substrate::Light => cell.light.max(0.) * dt,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Component {
+    pub substrate: Substrate,
+    pub reaction: Reaction,
+}
+
+/// Fraction of the gap between current and target expression closed per step.
+pub const EXPRESSION_RATE: f32 = STEP_SIZE;
+/// Proteins consumed per unit of expression raised, per component protein.
+pub const EXPRESSION_PROTEIN_COST: f32 = 0.001;
+/// pH shift per ATP spent by components, ATP hydrolysis releases protons.
+pub const ATP_HYDROLYSIS_PH_SHIFT: f32 = -0.01;
 
 #[derive(Debug, Clone, Copy)]
 pub struct ComponentProps {
     proteins: f32,
+    /// Protein count the component was built with and is maintained towards.
+    target_proteins: f32,
     pub speed: f32,
     pub efficiency: f32,
+    /// Current expression level in `[0, 1]`, scales throughput.
+    pub expression: f32,
+    /// pH at which the component runs at full efficiency.
+    pub ph_optimum: f32,
+    /// Distance from `ph_optimum` at which efficiency drops to `1/e`.
+    pub ph_tolerance: f32,
 }
 
 impl ComponentProps {
     pub fn new(proteins: f32, speed: f32) -> Self {
         Self {
             proteins,
+            target_proteins: proteins,
             speed,
             efficiency: get_efficiency(speed, proteins),
+            expression: 1.,
+            ph_optimum: NEUTRAL_PH,
+            ph_tolerance: 2.,
         }
     }
 
@@ -33,15 +87,74 @@ impl ComponentProps {
         self.proteins * PROTEIN_SIZE
     }
 
-    pub fn random() -> Self {
-        Self::new(rand::random::<f32>() * 1000., rand::random::<f32>())
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self {
+            ph_optimum: NEUTRAL_PH + rng.gen::<f32>() * 4. - 2.,
+            ph_tolerance: rng.gen::<f32>() * 3. + 0.5,
+            ..Self::new(rng.gen::<f32>() * 1000., rng.gen::<f32>())
+        }
+    }
+
+    /// Multiplier on efficiency from a Gaussian optimum curve around
+    /// `ph_optimum`.
+    pub fn ph_factor(&self, ph: f32) -> f32 {
+        let distance = (ph - self.ph_optimum) / self.ph_tolerance;
+        (-distance * distance).exp()
+    }
+
+    pub fn proteins(&self) -> f32 {
+        self.proteins
     }
 
-    pub fn get_input_output_amt(&self, constraint: f32) -> Amounts {
-        let input = (STEP_SIZE * self.speed).min(constraint);
+    /// Protein count maintenance rebuilds the component towards.
+    pub fn target_proteins(&self) -> f32 {
+        self.target_proteins
+    }
+
+    /// Hands half of the proteins to a daughter component. Both keep the
+    /// original target and rebuild towards it through maintenance.
+    pub fn split(&mut self) -> Self {
+        self.proteins *= 0.5;
+        self.efficiency = get_efficiency(self.speed, self.proteins);
+
+        *self
+    }
+
+    /// Proteins needed to bring the component back to full strength.
+    pub fn protein_deficit(&self) -> f32 {
+        (self.target_proteins - self.proteins).max(0.)
+    }
+
+    /// Removes the proteins lost to turnover this step and returns the amount.
+    pub fn degrade(&mut self, config: &CellConfig) -> f32 {
+        let lost = self.proteins * (1. - 0.5_f32.powf(STEP_SIZE / config.protein_half_life));
+        self.proteins -= lost;
+        self.efficiency = get_efficiency(self.speed, self.proteins);
+
+        lost
+    }
+
+    /// Incorporates `proteins` back into the component.
+    pub fn repair(&mut self, proteins: f32) {
+        self.proteins += proteins;
+        self.efficiency = get_efficiency(self.speed, self.proteins);
+    }
+
+    pub fn expression_cost(&self, expression_change: f32) -> f32 {
+        expression_change * self.proteins * EXPRESSION_PROTEIN_COST
+    }
+
+    /// Input the component would consume over `dt` if unconstrained.
+    pub fn demand(&self, dt: f32) -> f32 {
+        dt * self.speed * self.expression
+    }
+
+    /// Amounts for consuming `input` over `dt`.
+    pub fn amounts(&self, input: f32, dt: f32) -> Amounts {
         Amounts {
             input,
             output: input * self.efficiency,
+            dt,
         }
     }
 }
@@ -49,29 +162,56 @@ impl ComponentProps {
 pub struct Amounts {
     input: f32,
     output: f32,
+    /// Length of the integration step the amounts cover.
+    dt: f32,
 }
 
 impl Default for ComponentProps {
     fn default() -> Self {
         Self {
             proteins: 2.0,
+            target_proteins: 2.0,
             speed: 1.0,
             efficiency: get_efficiency(1.0, 2.0),
+            expression: 1.0,
+            ph_optimum: NEUTRAL_PH,
+            ph_tolerance: 2.0,
         }
     }
 }
 
 fn get_efficiency(speed: f32, proteins: f32) -> f32 {
+    if proteins <= 0. {
+        return 0.;
+    }
     1. / (1. + speed / proteins)
 }
 
-pub const COMPONENT_COUNT: usize = 5;
+pub const COMPONENT_COUNT: usize = 8;
+/// Display names, indexed like `get_components`.
+pub const COMPONENT_NAMES: [&str; COMPONENT_COUNT] = [
+    "flagella",
+    "chlorophyll",
+    "glycolysis",
+    "nucleotide synthesis",
+    "protein synthesis",
+    "adhesion",
+    "secretion",
+    "receptor",
+];
 pub fn get_components() -> [Component; COMPONENT_COUNT] {
+    let component = |substrate, reaction| Component {
+        substrate,
+        reaction,
+    };
     [
-        flangella::flangella,
-        chlorophyll::chlorophyll,
-        glycolysis::glycolysis,
-        nucleotide_de_novo::nucleotide_de_novo,
-        protein_de_novo::protein_de_novo,
+        component(Substrate::Atp, flangella::flangella),
+        component(Substrate::Light, chlorophyll::chlorophyll),
+        component(Substrate::Glucose, glycolysis::glycolysis),
+        component(Substrate::Atp, nucleotide_de_novo::nucleotide_de_novo),
+        component(Substrate::Atp, protein_de_novo::protein_de_novo),
+        component(Substrate::Atp, adhesion::adhesion),
+        component(Substrate::Atp, secretion::secretion),
+        component(Substrate::Atp, receptor::receptor),
     ]
 }
@@ -1,15 +1,17 @@
-use rapier2d::prelude::*;
-
-use crate::cell::chemicals::ATP_SIZE;
+use crate::cell::chemicals::{ATP_SIZE, WASTE_SIZE};
 use crate::cell::inner::PROTEIN_SIZE;
 use crate::cell::Cell;
 
-use super::ComponentProps;
+use super::{Amounts, ATP_HYDROLYSIS_PH_SHIFT};
+
+/// Waste produced per ATP spent.
+const WASTE_YIELD: f32 = 0.1;
 
-pub fn protein_de_novo(props: &ComponentProps, cell: &mut Cell) {
-    let amount = props.get_input_output_amt(cell.inner.chemicals.atp);
+pub fn protein_de_novo(cell: &mut Cell, amount: &Amounts) {
     cell.inner.chemicals.atp -= amount.input;
     cell.inner.proteins += amount.output;
+    cell.inner.chemicals.waste += amount.input * WASTE_YIELD;
 
-    cell.modify_size(amount.output * PROTEIN_SIZE - amount.input * ATP_SIZE);
+    cell.modify_size(amount.output * PROTEIN_SIZE + amount.input * (WASTE_YIELD * WASTE_SIZE - ATP_SIZE));
+    cell.modify_ph(amount.input * ATP_HYDROLYSIS_PH_SHIFT);
 }
@@ -0,0 +1,16 @@
+use crate::cell::chemicals::ATP_SIZE;
+use crate::cell::Cell;
+
+use super::{Amounts, ATP_HYDROLYSIS_PH_SHIFT};
+
+/// Binds signalling molecules around the cell. The more receptor throughput,
+/// the faster `Inner::received_signal` follows the outside level.
+pub fn receptor(cell: &mut Cell, amount: &Amounts) {
+    cell.inner.chemicals.atp -= amount.input;
+
+    let binding = amount.output.min(1.);
+    cell.inner.received_signal += (cell.surroundings.signal - cell.inner.received_signal) * binding;
+
+    cell.modify_size(-amount.input * ATP_SIZE);
+    cell.modify_ph(amount.input * ATP_HYDROLYSIS_PH_SHIFT);
+}
@@ -0,0 +1,15 @@
+use crate::cell::chemicals::ATP_SIZE;
+use crate::cell::Cell;
+
+use super::{Amounts, ATP_HYDROLYSIS_PH_SHIFT};
+
+/// Turns ATP into signalling molecules and pumps them straight out of the
+/// cell.
+pub fn secretion(cell: &mut Cell, amount: &Amounts) {
+    cell.inner.chemicals.atp -= amount.input;
+    cell.exported.signal += amount.output;
+    cell.surroundings.signal += amount.output;
+
+    cell.modify_size(-amount.input * ATP_SIZE);
+    cell.modify_ph(amount.input * ATP_HYDROLYSIS_PH_SHIFT);
+}
@@ -0,0 +1,67 @@
+/// Tunables shared by every cell, owned by `World` and passed down to
+/// `Cell::run_components`.
+#[derive(Debug, Clone, Copy)]
+pub struct CellConfig {
+    /// Simulated time for half of a component's proteins to degrade, where
+    /// each component step advances time by `STEP_SIZE`.
+    pub protein_half_life: f32,
+    /// ATP spent per protein used to repair a component.
+    pub maintenance_atp_cost: f32,
+    /// Age in simulated seconds at which cells die, `None` for immortal
+    /// cells.
+    pub max_lifespan: Option<f32>,
+    /// Size, in the units of `Cell::generate_size`, past which a cell bursts.
+    pub lysis_size: f32,
+    /// Water moved per step per unit of concentration difference across the
+    /// membrane.
+    pub osmotic_rate: f32,
+    /// Water an environment tile holds, turning its chemical amounts into
+    /// concentrations comparable with the cell's.
+    pub tile_water: f32,
+    /// Fraction of the distance to `NEUTRAL_PH` recovered per step.
+    pub ph_buffer_rate: f32,
+    /// Internal waste concentration above which components lose efficiency.
+    pub waste_tolerance: f32,
+    /// Internal waste concentration that kills the cell.
+    pub lethal_waste: f32,
+    /// Adhesins both cells need before they bond on contact.
+    pub adhesion_threshold: f32,
+    /// Gap between two cells' surfaces up to which they still count as
+    /// touching when bonding.
+    pub bond_tolerance: f32,
+    /// Largest `Genome::distance` at which two cells count as related.
+    pub kin_distance: f32,
+    /// Force above which a bond between two cells breaks.
+    pub bond_strength: f32,
+    /// Fraction of the concentration difference evened out across a bond per
+    /// update, in `[0, 1]`.
+    pub sharing_rate: f32,
+    /// Nucleotides consumed to replicate the genome on division, the cell
+    /// only divides once it has this many.
+    pub division_nucleotides: f32,
+    /// Free proteins a cell needs before dividing.
+    pub division_proteins: f32,
+}
+
+impl Default for CellConfig {
+    fn default() -> Self {
+        Self {
+            protein_half_life: 100.,
+            maintenance_atp_cost: 0.1,
+            max_lifespan: None,
+            lysis_size: 20000.,
+            osmotic_rate: 0.1,
+            tile_water: 100.,
+            ph_buffer_rate: 0.01,
+            waste_tolerance: 1.,
+            lethal_waste: 10.,
+            adhesion_threshold: 0.1,
+            bond_tolerance: 0.01,
+            kin_distance: 0.5,
+            bond_strength: 10000.,
+            sharing_rate: 0.1,
+            division_nucleotides: 20.,
+            division_proteins: 20.,
+        }
+    }
+}
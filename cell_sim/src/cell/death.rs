@@ -0,0 +1,18 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    /// Ran out of ATP.
+    Starvation,
+    /// Killed by another cell or by the user through `World::kill_cell`.
+    Predation,
+    /// Exceeded `CellConfig::max_lifespan`.
+    Age,
+    /// Burst after growing past `CellConfig::lysis_size`.
+    Lysis,
+    /// Poisoned by accumulated toxins.
+    Toxin,
+    /// Drifted out of an open world, see `Topology::Open`.
+    OutOfBounds,
+    /// Deleted by hand, see `World::remove_cells_within`. Its chemicals are
+    /// not released.
+    Removed,
+}
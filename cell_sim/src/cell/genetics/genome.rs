@@ -0,0 +1,339 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use rand::Rng;
+
+use crate::cell::component::COMPONENT_COUNT;
+use crate::cell::inner::Inner;
+
+pub const REGULATOR_COUNT: usize = 2;
+/// Expression programs per genome, i.e. how many cell types it can produce.
+pub const PROGRAM_COUNT: usize = 3;
+
+/// Internal concentration a regulator binds to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensor {
+    Atp,
+    Glucose,
+    Nucleotides,
+    Proteins,
+    /// External signal picked up by receptor components.
+    Signal,
+}
+
+impl Sensor {
+    const ALL: [Sensor; 5] = [
+        Sensor::Atp,
+        Sensor::Glucose,
+        Sensor::Nucleotides,
+        Sensor::Proteins,
+        Sensor::Signal,
+    ];
+
+    pub fn read(&self, inner: &Inner) -> f32 {
+        match self {
+            Sensor::Atp => inner.chemicals.atp,
+            Sensor::Glucose => inner.chemicals.glucose,
+            Sensor::Nucleotides => inner.nucleotides,
+            Sensor::Proteins => inner.proteins,
+            Sensor::Signal => inner.received_signal,
+        }
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self::ALL[rng.gen::<usize>() % Self::ALL.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegulatorKind {
+    Promoter,
+    Repressor,
+}
+
+/// A binding site that raises (promoter) or lowers (repressor) the expression
+/// of its gene as the sensed concentration rises past `threshold`.
+#[derive(Debug, Clone, Copy)]
+pub struct Regulator {
+    pub kind: RegulatorKind,
+    pub sensor: Sensor,
+    pub threshold: f32,
+    pub strength: f32,
+}
+
+impl Regulator {
+    pub fn distance(&self, other: &Regulator) -> f32 {
+        if self.kind != other.kind || self.sensor != other.sensor {
+            return 1.;
+        }
+        ((self.threshold - other.threshold).abs() / 10. + (self.strength - other.strength).abs())
+            .min(1.)
+    }
+
+    /// Hill-type response in `[0, 1]`, 0.5 at `threshold`.
+    pub fn activation(&self, inner: &Inner) -> f32 {
+        let value = self.sensor.read(inner).max(0.);
+        let value = value * value;
+        let threshold = self.threshold * self.threshold;
+        if value + threshold <= 0. {
+            return 0.;
+        }
+        value / (value + threshold)
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self {
+            kind: match rng.gen::<bool>() {
+                true => RegulatorKind::Promoter,
+                false => RegulatorKind::Repressor,
+            },
+            sensor: Sensor::random(rng),
+            threshold: rng.gen::<f32>() * 10.,
+            strength: rng.gen::<f32>(),
+        }
+    }
+}
+
+/// Regulatory region of a single component.
+#[derive(Debug, Clone, Copy)]
+pub struct Gene {
+    /// Expression level with no regulator bound.
+    pub basal: f32,
+    pub regulators: [Option<Regulator>; REGULATOR_COUNT],
+}
+
+impl Gene {
+    pub fn distance(&self, other: &Gene) -> f32 {
+        let regulators = self
+            .regulators
+            .iter()
+            .zip(other.regulators.iter())
+            .map(|pair| match pair {
+                (Some(a), Some(b)) => a.distance(b),
+                (None, None) => 0.,
+                _ => 1.,
+            })
+            .sum::<f32>();
+
+        (self.basal - other.basal).abs() + regulators
+    }
+
+    pub fn target_expression(&self, inner: &Inner) -> f32 {
+        let expression = self
+            .regulators
+            .iter()
+            .flatten()
+            .fold(self.basal, |expression, regulator| {
+                let effect = regulator.activation(inner) * regulator.strength;
+                match regulator.kind {
+                    RegulatorKind::Promoter => expression + effect,
+                    RegulatorKind::Repressor => expression - effect,
+                }
+            });
+
+        expression.clamp(0., 1.)
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let mut regulators = [None; REGULATOR_COUNT];
+        regulators.iter_mut().for_each(|regulator| {
+            if rng.gen::<bool>() {
+                *regulator = Some(Regulator::random(rng));
+            }
+        });
+
+        Self {
+            basal: rng.gen::<f32>(),
+            regulators,
+        }
+    }
+}
+
+impl Default for Gene {
+    fn default() -> Self {
+        Self {
+            basal: 1.,
+            regulators: [None; REGULATOR_COUNT],
+        }
+    }
+}
+
+/// One gene per component, indexed the same way as `Cell::components`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Program {
+    pub genes: [Gene; COMPONENT_COUNT],
+}
+
+impl Program {
+    pub fn distance(&self, other: &Program) -> f32 {
+        self.genes
+            .iter()
+            .zip(other.genes.iter())
+            .map(|(a, b)| a.distance(b))
+            .sum()
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let mut genes = [Gene::default(); COMPONENT_COUNT];
+        genes.iter_mut().for_each(|gene| *gene = Gene::random(rng));
+
+        Self { genes }
+    }
+}
+
+/// Condition under which a daughter cell switches to a program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cue {
+    /// The parent had at least this many bonds, i.e. sat inside a colony
+    /// rather than on its edge.
+    Bonds(usize),
+    /// The daughter's sensed concentration is at least `threshold`.
+    Concentration { sensor: Sensor, threshold: f32 },
+}
+
+impl Cue {
+    pub fn matches(&self, bonds: usize, inner: &Inner) -> bool {
+        match *self {
+            Cue::Bonds(min) => bonds >= min,
+            Cue::Concentration { sensor, threshold } => sensor.read(inner) >= threshold,
+        }
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        match rng.gen::<bool>() {
+            true => Cue::Bonds(rng.gen::<usize>() % 4 + 1),
+            false => Cue::Concentration {
+                sensor: Sensor::random(rng),
+                threshold: rng.gen::<f32>() * 10.,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Genome {
+    /// Program 0 is the default cell type, the others are only expressed
+    /// when their cue matches at division.
+    pub programs: [Program; PROGRAM_COUNT],
+    pub cues: [Option<Cue>; PROGRAM_COUNT],
+}
+
+impl Genome {
+    /// How far apart two genomes are, 0 for identical ones.
+    pub fn distance(&self, other: &Genome) -> f32 {
+        let cues = self
+            .cues
+            .iter()
+            .zip(other.cues.iter())
+            .filter(|(a, b)| a != b)
+            .count() as f32;
+
+        self.programs
+            .iter()
+            .zip(other.programs.iter())
+            .map(|(a, b)| a.distance(b))
+            .sum::<f32>()
+            + cues
+    }
+
+    /// Hash of the exact genome, equal for cells of the same species.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.programs.iter().flat_map(|program| program.genes.iter()).for_each(|gene| {
+            gene.basal.to_bits().hash(&mut hasher);
+            gene.regulators.iter().for_each(|regulator| match regulator {
+                Some(regulator) => {
+                    (regulator.kind as u8, regulator.sensor as u8).hash(&mut hasher);
+                    regulator.threshold.to_bits().hash(&mut hasher);
+                    regulator.strength.to_bits().hash(&mut hasher);
+                }
+                None => 0xff_u8.hash(&mut hasher),
+            });
+        });
+        self.cues.iter().for_each(|cue| match cue {
+            Some(Cue::Bonds(min)) => (0_u8, *min).hash(&mut hasher),
+            Some(Cue::Concentration { sensor, threshold }) => {
+                (1_u8, *sensor as u8, threshold.to_bits()).hash(&mut hasher)
+            }
+            None => 2_u8.hash(&mut hasher),
+        });
+
+        hasher.finish()
+    }
+
+    pub fn is_related(&self, other: &Genome, kin_distance: f32) -> bool {
+        self.distance(other) <= kin_distance
+    }
+
+    /// Picks the program a daughter cell expresses: the last one whose cue
+    /// matches, or 0 if none does.
+    pub fn select_program(&self, bonds: usize, inner: &Inner) -> usize {
+        self.cues
+            .iter()
+            .enumerate()
+            .skip(1)
+            .rev()
+            .find(|(_, cue)| cue.is_some_and(|cue| cue.matches(bonds, inner)))
+            .map(|(program, _)| program)
+            .unwrap_or(0)
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let mut programs = [Program::default(); PROGRAM_COUNT];
+        programs.iter_mut().for_each(|program| *program = Program::random(rng));
+        let mut cues = [None; PROGRAM_COUNT];
+        cues.iter_mut().skip(1).for_each(|cue| {
+            if rng.gen::<bool>() {
+                *cue = Some(Cue::random(rng));
+            }
+        });
+
+        Self { programs, cues }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regulation() {
+        let mut inner = Inner::default();
+        let gene = Gene {
+            basal: 0.,
+            regulators: [
+                Some(Regulator {
+                    kind: RegulatorKind::Promoter,
+                    sensor: Sensor::Glucose,
+                    threshold: 1.,
+                    strength: 1.,
+                }),
+                None,
+            ],
+        };
+
+        assert_eq!(gene.target_expression(&inner), 0.);
+        inner.chemicals.glucose = 1.;
+        assert!((gene.target_expression(&inner) - 0.5).abs() < 1e-6);
+        inner.chemicals.glucose = 100.;
+        assert!(gene.target_expression(&inner) > 0.99);
+    }
+
+    #[test]
+    fn test_select_program() {
+        let mut genome = Genome::default();
+        genome.cues[1] = Some(Cue::Bonds(2));
+        genome.cues[2] = Some(Cue::Concentration {
+            sensor: Sensor::Atp,
+            threshold: 5.,
+        });
+        let mut inner = Inner::default();
+
+        assert_eq!(genome.select_program(0, &inner), 0);
+        assert_eq!(genome.select_program(3, &inner), 1);
+        inner.chemicals.atp = 10.;
+        assert_eq!(genome.select_program(0, &inner), 2);
+        assert_eq!(genome.fingerprint(), genome.fingerprint());
+        assert_ne!(genome.fingerprint(), Genome::default().fingerprint());
+    }
+}
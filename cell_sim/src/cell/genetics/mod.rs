@@ -1,2 +1,2 @@
 mod cell_builder;
-mod rna;
+pub mod genome;
@@ -1,3 +0,0 @@
-pub struct RNA {
-    
-}
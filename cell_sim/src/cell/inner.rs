@@ -2,6 +2,10 @@ use super::chemicals::Chemicals;
 
 pub const PROTEIN_SIZE: f32 = 1.0;
 pub const NUCLEOTIDE_SIZE: f32 = 1.0;
+pub const WATER_SIZE: f32 = 1.0;
+pub const NEUTRAL_PH: f32 = 7.0;
+/// Cells never shrink below this much water.
+pub const MIN_WATER: f32 = 0.1;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Inner {
@@ -9,11 +13,35 @@ pub struct Inner {
     pub nucleotides: f32,
     pub proteins: f32,
     pub ph: f32,
-    pub test: f32,
+    pub water: f32,
+    /// Signal level bound by receptors, what gene regulation sees.
+    pub received_signal: f32,
 }
 
 impl Inner {
     pub fn size(&self) -> f32 {
-        self.test
+        self.water * WATER_SIZE
+            + self.chemicals.size()
+            + self.nucleotides * NUCLEOTIDE_SIZE
+            + self.proteins * PROTEIN_SIZE
+    }
+
+    /// Amount of dissolved molecules, regardless of their size.
+    pub fn solutes(&self) -> f32 {
+        self.chemicals.solutes() + self.nucleotides + self.proteins
+    }
+
+    /// Halves every amount, keeping one half and returning the other.
+    pub fn split(&mut self) -> Inner {
+        self.chemicals = self.chemicals * 0.5;
+        self.nucleotides *= 0.5;
+        self.proteins *= 0.5;
+        self.water *= 0.5;
+
+        *self
+    }
+
+    pub fn concentration(&self) -> f32 {
+        self.solutes() / self.water.max(MIN_WATER)
     }
 }
@@ -0,0 +1,111 @@
+use crate::STEP_SIZE;
+
+use super::component::{get_components, Amounts, Substrate, COMPONENT_COUNT};
+use super::Cell;
+
+/// Largest share of a pool the components may draw in one substep before
+/// the step is split.
+const MAX_DRAW: f32 = 0.5;
+/// Upper bound on substeps per `STEP_SIZE`.
+const MAX_SUBSTEPS: usize = 8;
+const SUBSTRATES: usize = Substrate::ALL.len();
+
+impl Cell {
+    /// Runs every component over one `STEP_SIZE`. All components are
+    /// evaluated against the same state before any reaction is applied, and
+    /// consumers of a pool that cannot cover them all get the same fraction
+    /// of their demand, so no pool goes negative and component order does
+    /// not matter. Steps that would drain a pool quickly are split into
+    /// substeps, letting producers refill it in between.
+    pub(super) fn integrate(&mut self, efficiency_factor: f32) {
+        let substeps = self.substeps();
+        let dt = STEP_SIZE / substeps as f32;
+        (0..substeps).for_each(|_| self.substep(dt, efficiency_factor));
+    }
+
+    fn demands(&self, dt: f32) -> [f32; COMPONENT_COUNT] {
+        let mut demands = [0.; COMPONENT_COUNT];
+        demands
+            .iter_mut()
+            .zip(self.components.iter())
+            .for_each(|(demand, props)| {
+                if let Some(props) = props {
+                    *demand = props.demand(dt);
+                }
+            });
+
+        demands
+    }
+
+    /// Fraction of its demand each substrate can satisfy over `dt`.
+    fn grants(&self, demands: &[f32; COMPONENT_COUNT], dt: f32) -> [f32; SUBSTRATES] {
+        let mut grants = [1.; SUBSTRATES];
+        grants
+            .iter_mut()
+            .zip(Substrate::ALL.iter().zip(substrate_demands(demands)))
+            .for_each(|(grant, (substrate, demand))| {
+                if demand > 0. {
+                    *grant = (substrate.available(self, dt) / demand).min(1.);
+                }
+            });
+
+        grants
+    }
+
+    fn substeps(&self) -> usize {
+        let demands = substrate_demands(&self.demands(STEP_SIZE));
+        let draw = Substrate::ALL
+            .iter()
+            .zip(demands)
+            .filter(|(substrate, _)| **substrate != Substrate::Light)
+            .map(|(substrate, demand)| {
+                let available = substrate.available(self, STEP_SIZE);
+                match available > 0. {
+                    true => demand / available,
+                    false => 0.,
+                }
+            })
+            .fold(0., f32::max);
+
+        ((draw / MAX_DRAW).ceil() as usize).clamp(1, MAX_SUBSTEPS)
+    }
+
+    fn substep(&mut self, dt: f32, efficiency_factor: f32) {
+        let components = get_components();
+        let demands = self.demands(dt);
+        let grants = self.grants(&demands, dt);
+        let ph = self.inner.ph;
+        // What is left of each pool, so rounding in the grants cannot draw
+        // more than it holds
+        let mut remaining = Substrate::ALL.map(|substrate| substrate.available(self, dt));
+        let amounts: [Option<Amounts>; COMPONENT_COUNT] = std::array::from_fn(|index| {
+            let mut props = self.components[index]?;
+            props.efficiency *= props.ph_factor(ph) * efficiency_factor;
+            let substrate = components[index].substrate as usize;
+            let input = (demands[index] * grants[substrate]).min(remaining[substrate]);
+            remaining[substrate] -= input;
+
+            Some(props.amounts(input, dt))
+        });
+
+        components
+            .iter()
+            .zip(amounts.iter())
+            .for_each(|(component, amounts)| {
+                if let Some(amounts) = amounts {
+                    (component.reaction)(self, amounts);
+                }
+            });
+    }
+}
+
+/// Total demand on each substrate, indexed like `Substrate::ALL`.
+fn substrate_demands(demands: &[f32; COMPONENT_COUNT]) -> [f32; SUBSTRATES] {
+    let mut totals = [0.; SUBSTRATES];
+    get_components()
+        .iter()
+        .zip(demands.iter())
+        .for_each(|(component, demand)| totals[component.substrate as usize] += demand);
+
+    totals
+}
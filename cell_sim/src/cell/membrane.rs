@@ -1,8 +1,39 @@
+use rand::Rng;
+
+use super::chemicals::Chemicals;
+
 #[derive(Debug, Clone, Copy, Default)]
-pub struct Membrane {}
+pub struct Membrane {
+    /// Rate at which small molecules diffuse through the membrane.
+    pub permeability: f32,
+}
 
 impl Membrane {
     pub fn size(&self) -> f32 {
         0.0
     }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self {
+            permeability: rng.gen::<f32>(),
+        }
+    }
+
+    /// Waste leaving the cell this step by passive diffusion, negative when it
+    /// flows in. Limited by what is available on either side, where slightly
+    /// negative amounts left by diffusion count as none. `tile_water`
+    /// dilutes the waste in `surroundings`.
+    pub fn waste_flux(
+        &self,
+        inside: f32,
+        water: f32,
+        surroundings: &Chemicals,
+        tile_water: f32,
+        step: f32,
+    ) -> f32 {
+        let gradient = inside / water - surroundings.waste / tile_water;
+        (gradient * self.permeability * step)
+            .max(-surroundings.waste.max(0.))
+            .min(inside.max(0.))
+    }
 }
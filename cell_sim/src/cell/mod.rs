@@ -1,22 +1,51 @@
 pub mod chemicals;
 pub mod component;
-mod inner;
+pub mod config;
+pub mod death;
+pub mod genetics;
+pub(crate) mod inner;
+mod integrator;
 mod membrane;
 
 use nalgebra::{Vector2, vector};
+use rand::Rng;
 
-use self::chemicals::Chemicals;
-use self::component::{ComponentProps, COMPONENT_COUNT, get_components};
-use self::inner::Inner;
+use self::chemicals::{Chemicals, ATP_SIZE, GLUCOSE_SIZE, WASTE_SIZE};
+use self::component::{ComponentProps, COMPONENT_COUNT, EXPRESSION_RATE};
+use self::config::CellConfig;
+use self::death::DeathCause;
+use self::genetics::genome::Genome;
+use self::inner::{Inner, MIN_WATER, NEUTRAL_PH, NUCLEOTIDE_SIZE, PROTEIN_SIZE, WATER_SIZE};
 use self::membrane::Membrane;
+use crate::STEP_SIZE;
 
 
 #[derive(Clone, Copy, Default)]
 pub struct Cell {
-    pub dead: bool,
+    pub death: Option<DeathCause>,
+    /// Simulated seconds since the cell was created, on the same clock as
+    /// `World::time`.
+    pub age: f32,
     pub inner: Inner,
     pub membrane: Membrane,
     pub components: [Option<ComponentProps>; COMPONENT_COUNT],
+    pub genome: Genome,
+    /// Index of the genome program this cell expresses.
+    pub cell_type: usize,
+    /// Divisions since the founding cell, which is generation 0.
+    pub generation: u32,
+    /// Chemicals in the environment around the cell, refreshed by `World`
+    /// before every update.
+    pub surroundings: Chemicals,
+    /// Chemicals released through the membrane since `World` last moved them
+    /// into the environment. Negative amounts were taken up.
+    pub exported: Chemicals,
+    /// Light reaching the cell, refreshed by `World` along with
+    /// `surroundings`.
+    pub light: f32,
+    /// Surface adhesins produced by the adhesion component, see
+    /// `CellConfig::adhesion_threshold`.
+    pub adhesion: f32,
     size: f32,
     pub impulse: Vector2<f32>,
     pub size_changed: bool,
@@ -28,6 +57,7 @@ impl Cell {
         inner: Inner,
         membrane: Membrane,
         components: [Option<ComponentProps>; COMPONENT_COUNT],
+        genome: Genome,
     ) -> Self {
         let mut size = inner.size() + membrane.size();
         components.iter().flatten().for_each(|component| {
@@ -35,10 +65,18 @@ impl Cell {
         });
 
         Self {
-            dead: false,
+            death: None,
+            age: 0.,
             inner,
             membrane,
             components,
+            genome,
+            cell_type: 0,
+            generation: 0,
+            surroundings: Chemicals::default(),
+            exported: Chemicals::default(),
+            light: 0.,
+            adhesion: 0.,
             size,
             size_changed: false,
             impulse: vector![0.0, 0.0],
@@ -46,6 +84,36 @@ impl Cell {
         }
     }
 
+    pub fn is_dead(&self) -> bool {
+        self.death.is_some()
+    }
+
+    /// Marks the cell as dead. The first cause recorded is kept.
+    pub fn kill(&mut self, cause: DeathCause) {
+        if self.death.is_none() {
+            self.death = Some(cause);
+        }
+    }
+
+    /// What the cell releases into the environment when it dies: its
+    /// chemicals, plus its proteins and nucleotides broken down into glucose
+    /// of the same size.
+    pub fn remains(&self) -> Chemicals {
+        let components: f32 = self.components.iter().flatten().map(ComponentProps::size).sum();
+        let broken_down = self.inner.proteins * PROTEIN_SIZE
+            + self.inner.nucleotides * NUCLEOTIDE_SIZE
+            + components;
+
+        Chemicals {
+            glucose: self.inner.chemicals.glucose + broken_down / GLUCOSE_SIZE,
+            ..self.inner.chemicals
+        }
+    }
+
+    pub fn is_adhesive(&self, config: &CellConfig) -> bool {
+        self.adhesion >= config.adhesion_threshold
+    }
+
     pub fn modify_size(&mut self, size_change: f32) {
         self.size += size_change;
         self.size_changed = true;
@@ -56,29 +124,40 @@ impl Cell {
         self.size * 0.001
     }
 
+    /// Shifts pH by `shift` diluted in the cell's water.
+    pub fn modify_ph(&mut self, shift: f32) {
+        self.inner.ph += shift / self.inner.water.max(MIN_WATER);
+    }
+
     pub fn modify_impulse(&mut self, vel: Vector2<f32>) {
         self.impulse += vel;
         self.velocity_changed = true;
     }
 
     pub fn new_random() -> Self {
+        Self::new_random_with(&mut rand::thread_rng())
+    }
+
+    /// Random cell drawn from `rng`, so a seeded one gives the same cell.
+    pub fn new_random_with(rng: &mut impl Rng) -> Self {
         let inner = Inner {
             chemicals: Chemicals {
-                atp: rand::random::<f32>() * 10.,
+                atp: rng.gen::<f32>() * 10.,
                 ..Default::default()
             },
-            ph: rand::random::<f32>() * 10.,
-            test: rand::random::<f32>() * 10.,
-            nucleotides: rand::random::<f32>() * 10.,
-            proteins: rand::random::<f32>() + 10.,
+            ph: NEUTRAL_PH + rng.gen::<f32>() * 2. - 1.,
+            water: rng.gen::<f32>() * 10. + MIN_WATER,
+            nucleotides: rng.gen::<f32>() * 10.,
+            proteins: rng.gen::<f32>() + 10.,
+            received_signal: 0.,
         };
-        let membrane = Membrane {};
+        let membrane = Membrane::random(rng);
         let mut components = [None; COMPONENT_COUNT];
         (0..COMPONENT_COUNT).for_each(|i| {
-            components[i] = Some(ComponentProps::random());
+            components[i] = Some(ComponentProps::random(rng));
         });
 
-        Self::new(inner, membrane, components)
+        Self::new(inner, membrane, components, Genome::random(rng))
     }
 
     pub fn inject_component(&mut self, compoent_index: usize, component: ComponentProps) {
@@ -91,6 +170,40 @@ impl Cell {
         self.size_changed = true;
     }
 
+    pub fn should_divide(&self, config: &CellConfig) -> bool {
+        !self.is_dead()
+            && self.inner.nucleotides >= config.division_nucleotides
+            && self.inner.proteins >= config.division_proteins
+    }
+
+    /// Replicates the genome and splits the cell's contents in half, returning
+    /// the daughter. The daughter keeps the parent's cell type until `World`
+    /// picks its program.
+    pub fn divide(&mut self, config: &CellConfig) -> Cell {
+        self.inner.nucleotides -= config.division_nucleotides;
+        let inner = self.inner.split();
+        let mut components = self.components;
+        self.components
+            .iter_mut()
+            .zip(components.iter_mut())
+            .for_each(|(parent, daughter)| {
+                if let Some(parent) = parent {
+                    *daughter = Some(parent.split());
+                }
+            });
+
+        self.size = self.generate_size();
+        self.size_changed = true;
+
+        Cell {
+            cell_type: self.cell_type,
+            generation: self.generation + 1,
+            adhesion: self.adhesion,
+            light: self.light,
+            ..Cell::new(inner, self.membrane, components, self.genome)
+        }
+    }
+
     pub fn generate_size(&self) -> f32 {
         let mut size = 0.0;
         self.components.iter().flatten().for_each(|component| {
@@ -100,23 +213,319 @@ impl Cell {
         size + self.inner.size() + self.membrane.size()
     }
 
-    #[rustfmt::skip]
-    pub fn run_components(&mut self) {
-        let component_functions = get_components();
-        let component_props = self.components;
-        component_functions
+    /// Moves each component's expression towards the level its gene asks for
+    /// given the current internal state. Raising expression consumes proteins
+    /// from `Inner::proteins`, so a starved cell cannot ramp up.
+    pub fn regulate(&mut self) {
+        let inner = &mut self.inner;
+        let mut size_change = 0.;
+        self.genome.programs[self.cell_type]
+            .genes
             .iter()
-            .zip(component_props.iter())
-            .for_each(|(component_function, component_props_option)| 
-                match component_props_option {
-                    Some(component_props) => {
-                        component_function(component_props, self);
+            .zip(self.components.iter_mut())
+            .for_each(|(gene, component_option)| {
+                if let Some(component) = component_option {
+                    let target = gene.target_expression(inner);
+                    let mut change = (target - component.expression) * EXPRESSION_RATE;
+                    if change > 0. {
+                        let cost = component.expression_cost(change);
+                        if cost > inner.proteins {
+                            change *= inner.proteins / cost;
+                        }
+                        let cost = component.expression_cost(change);
+                        inner.proteins -= cost;
+                        size_change -= cost * PROTEIN_SIZE;
                     }
-                    None => {}
-                },
-            );
+                    component.expression += change;
+                }
+            });
+
+        if size_change != 0. {
+            self.modify_size(size_change);
+        }
+    }
+
+    /// Degrades every component's proteins and repairs them from
+    /// `Inner::proteins`, paying ATP per protein replaced. Components that
+    /// cannot be repaired lose efficiency.
+    pub fn maintain(&mut self, config: &CellConfig) {
+        let inner = &mut self.inner;
+        let mut size_change = 0.;
+        self.components.iter_mut().flatten().for_each(|component| {
+            size_change -= component.degrade(config) * PROTEIN_SIZE;
+
+            let mut repair = component.protein_deficit().min(inner.proteins);
+            if config.maintenance_atp_cost > 0. {
+                repair = repair.min(inner.chemicals.atp.max(0.) / config.maintenance_atp_cost);
+            }
+            let atp_cost = repair * config.maintenance_atp_cost;
+            inner.proteins -= repair;
+            inner.chemicals.atp -= atp_cost;
+            size_change -= atp_cost * ATP_SIZE;
+            component.repair(repair);
+        });
+
+        if size_change != 0. {
+            self.modify_size(size_change);
+        }
+    }
+
+    /// Moves water across the membrane down the concentration gradient between
+    /// the cell and its surroundings, swelling or shrinking the cell.
+    pub fn osmose(&mut self, config: &CellConfig) {
+        let outside = self.surroundings.solutes() / config.tile_water;
+        let gradient = self.inner.concentration() - outside;
+        let water = (self.inner.water + gradient * config.osmotic_rate * STEP_SIZE).max(MIN_WATER);
+        let change = water - self.inner.water;
+        self.inner.water = water;
+        self.modify_size(change * WATER_SIZE);
+    }
+
+    /// Lets waste diffuse through the membrane.
+    pub fn transport(&mut self, config: &CellConfig) {
+        let flux = self.membrane.waste_flux(
+            self.inner.chemicals.waste,
+            self.inner.water.max(MIN_WATER),
+            &self.surroundings,
+            config.tile_water,
+            STEP_SIZE,
+        );
+        self.inner.chemicals.waste -= flux;
+        self.surroundings.waste += flux;
+        self.exported.waste += flux;
+        self.modify_size(-flux * WASTE_SIZE);
+    }
+
+    pub fn waste_concentration(&self) -> f32 {
+        self.inner.chemicals.waste / self.inner.water.max(MIN_WATER)
+    }
+
+    /// Multiplier on component efficiency once waste passes
+    /// `CellConfig::waste_tolerance`.
+    pub fn waste_factor(&self, config: &CellConfig) -> f32 {
+        let concentration = self.waste_concentration();
+        if concentration > config.waste_tolerance {
+            config.waste_tolerance / concentration
+        } else {
+            1.
+        }
+    }
+
+    /// Takes in `chemicals` and `nucleotides` from a neighbour, or gives them
+    /// away when negative. Amounts never drop below zero.
+    pub fn exchange(&mut self, chemicals: Chemicals, nucleotides: f32) {
+        let before = self.inner.chemicals.size() + self.inner.nucleotides * NUCLEOTIDE_SIZE;
+        self.inner.chemicals += chemicals;
+        self.inner.chemicals.clamp_non_negative();
+        self.inner.nucleotides = (self.inner.nucleotides + nucleotides).max(0.);
+        let after = self.inner.chemicals.size() + self.inner.nucleotides * NUCLEOTIDE_SIZE;
+        self.modify_size(after - before);
+    }
+
+    /// Ages the cell by `dt` simulated seconds, killing it once it reaches
+    /// `CellConfig::max_lifespan`.
+    pub fn grow_older(&mut self, dt: f32, config: &CellConfig) {
+        self.age += dt;
+        if config.max_lifespan.is_some_and(|max_lifespan| self.age >= max_lifespan) {
+            self.kill(DeathCause::Age);
+        }
+    }
+
+    /// Relaxes pH back towards neutral, the shifts come from the components.
+    pub fn buffer_ph(&mut self, config: &CellConfig) {
+        self.inner.ph += (NEUTRAL_PH - self.inner.ph) * config.ph_buffer_rate;
+    }
+
+    #[rustfmt::skip]
+    pub fn run_components(&mut self, config: &CellConfig) {
+        self.regulate();
+        self.maintain(config);
+        self.osmose(config);
+        self.transport(config);
+        self.buffer_ph(config);
+        self.integrate(self.waste_factor(config));
+
         if self.inner.chemicals.atp <= 0. {
-            self.dead = true;
+            self.kill(DeathCause::Starvation);
+        }
+        if self.size > config.lysis_size {
+            self.kill(DeathCause::Lysis);
+        }
+        if self.waste_concentration() > config.lethal_waste {
+            self.kill(DeathCause::Toxin);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waste() {
+        let config = CellConfig::default();
+        let mut cell = Cell::new_random();
+        cell.membrane.permeability = 1.;
+        cell.inner.water = 1.;
+        cell.inner.chemicals.waste = 0.5;
+        cell.transport(&config);
+        assert!(cell.exported.waste > 0.);
+        assert!(cell.inner.chemicals.waste < 0.5);
+
+        // A tile diffusion left slightly negative gives nothing
+        cell.inner.chemicals.waste = 0.;
+        cell.surroundings.waste = -0.1;
+        cell.transport(&config);
+        assert!(cell.inner.chemicals.waste >= 0.);
+        cell.surroundings.waste = f32::NAN;
+        cell.transport(&config);
+
+        cell.inner.chemicals.waste = config.lethal_waste * 2.;
+        cell.inner.chemicals.atp = 100.;
+        cell.run_components(&config);
+        assert_eq!(cell.death, Some(DeathCause::Toxin));
+    }
+
+    #[test]
+    fn test_integrate_conserves() {
+        // Both syntheses want far more ATP than there is, the size tracked
+        // through the reactions must still match the cell's contents
+        let mut cell = Cell::new_random();
+        cell.components = [None; COMPONENT_COUNT];
+        let mut synthesis = ComponentProps::default();
+        synthesis.speed = 1000.;
+        cell.components[3] = Some(synthesis);
+        cell.components[4] = Some(synthesis);
+        cell.inner.chemicals.atp = 0.01;
+        cell.size = cell.generate_size();
+        let nucleotides = cell.inner.nucleotides;
+
+        cell.integrate(1.);
+        assert!(cell.inner.chemicals.atp >= 0.);
+        assert!(cell.inner.nucleotides > nucleotides);
+        assert!((cell.size - cell.generate_size()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_maintenance() {
+        // Half of the proteins degrade every step
+        let config = CellConfig {
+            protein_half_life: STEP_SIZE,
+            ..CellConfig::default()
+        };
+        let healthy = ComponentProps::default();
+        let mut props = healthy;
+        assert!((props.degrade(&config) - 1.).abs() < 1e-6);
+        assert!((props.proteins() - 1.).abs() < 1e-6);
+        assert!(props.efficiency < healthy.efficiency);
+        props.repair(props.protein_deficit());
+        assert_eq!(props.proteins(), healthy.proteins());
+        assert_eq!(props.efficiency, healthy.efficiency);
+
+        let mut cell = Cell::new_random();
+        cell.components = [None; COMPONENT_COUNT];
+        cell.components[0] = Some(healthy);
+        cell.inner.proteins = 10.;
+        cell.inner.chemicals.atp = 10.;
+        cell.maintain(&config);
+        let props = cell.components[0].unwrap();
+        assert!((props.proteins() - 2.).abs() < 1e-6);
+        assert!((cell.inner.proteins - 9.).abs() < 1e-6);
+        assert!((cell.inner.chemicals.atp - (10. - config.maintenance_atp_cost)).abs() < 1e-6);
+
+        // Without spare proteins or ATP the component wears down
+        for (proteins, atp) in [(0., 10.), (10., 0.)] {
+            cell.components[0] = Some(healthy);
+            cell.inner.proteins = proteins;
+            cell.inner.chemicals.atp = atp;
+            cell.maintain(&config);
+            let props = cell.components[0].unwrap();
+            assert!((props.proteins() - 1.).abs() < 1e-6);
+            assert!(props.efficiency < healthy.efficiency);
+            assert_eq!(cell.inner.proteins, proteins);
+            assert_eq!(cell.inner.chemicals.atp, atp);
+        }
+    }
+
+    /// Cell running glycolysis alone on plenty of glucose.
+    fn fermenting_cell(ph: f32) -> Cell {
+        let mut cell = Cell::new_random();
+        cell.components = [None; COMPONENT_COUNT];
+        cell.components[2] = Some(ComponentProps::default());
+        cell.inner.chemicals.atp = 0.;
+        cell.inner.chemicals.glucose = 10.;
+        cell.inner.water = 1.;
+        cell.inner.ph = ph;
+        cell
+    }
+
+    #[test]
+    fn test_ph() {
+        let config = CellConfig::default();
+        let mut neutral = fermenting_cell(NEUTRAL_PH);
+        neutral.integrate(1.);
+        assert!(neutral.inner.ph < NEUTRAL_PH);
+
+        let shifted = neutral.inner.ph;
+        neutral.buffer_ph(&config);
+        assert!(neutral.inner.ph > shifted && neutral.inner.ph < NEUTRAL_PH);
+
+        let mut acidic = fermenting_cell(NEUTRAL_PH - 4.);
+        acidic.integrate(1.);
+        assert!(acidic.inner.chemicals.atp > 0.);
+        assert!(acidic.inner.chemicals.atp < neutral.inner.chemicals.atp);
+    }
+
+    #[test]
+    fn test_osmosis() {
+        let config = CellConfig::default();
+        let mut cell = Cell::new_random();
+        cell.inner.water = 1.;
+        cell.surroundings = Chemicals::default();
+        let concentration = cell.inner.concentration();
+
+        // Water follows the solutes into the cell
+        cell.osmose(&config);
+        assert!(cell.inner.water > 1.);
+
+        // A tile as concentrated as the cell holds far more solutes
+        cell.inner.water = 1.;
+        cell.surroundings.glucose = concentration * config.tile_water;
+        cell.osmose(&config);
+        assert!((cell.inner.water - 1.).abs() < 1e-4);
+
+        cell.surroundings.glucose *= 2.;
+        cell.osmose(&config);
+        assert!(cell.inner.water < 1.);
+    }
+
+    #[test]
+    fn test_signalling() {
+        let config = CellConfig::default();
+        let mut cell = Cell::new_random();
+        cell.components = [Some(ComponentProps::default()); COMPONENT_COUNT];
+        cell.genome = Genome::default();
+        cell.inner.chemicals.atp = 100.;
+        cell.surroundings.signal = 10.;
+        (0..100).for_each(|_| cell.run_components(&config));
+
+        assert!(cell.exported.signal > 0.);
+        assert!(cell.inner.received_signal > 1.);
+    }
+
+    #[test]
+    fn test_integrator() {
+        let mut cell = Cell::new_random();
+        cell.components = [Some(ComponentProps::default()); COMPONENT_COUNT];
+        cell.inner.chemicals.atp = 0.001;
+        cell.inner.chemicals.glucose = 0.;
+        cell.inner.nucleotides = 0.;
+        cell.inner.proteins = 0.;
+        cell.light = 0.;
+
+        cell.integrate(1.);
+        assert!(cell.inner.chemicals.atp >= 0.);
+        assert!(cell.inner.nucleotides > 0.);
+        assert!((cell.inner.nucleotides - cell.inner.proteins).abs() < 1e-9);
+    }
+}
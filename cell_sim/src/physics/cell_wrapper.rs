@@ -8,4 +8,11 @@ pub struct CellWrapper {
     pub collider_handle: ColliderHandle,
     pub rigid_body_handle: RigidBodyHandle,
     pub index: usize,
+    /// Unique over the lifetime of the world, unlike `index` which is
+    /// reused once the cell dies.
+    pub id: u64,
+    /// `id` of the cell this one divided from.
+    pub parent: Option<u64>,
+    /// `id` of the ancestor that was added to the world directly.
+    pub founder: u64,
 }
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+
+use nalgebra::Point2;
+use rapier2d::dynamics::{ImpulseJointHandle, RevoluteJointBuilder, RigidBodyHandle};
+
+use super::world::World;
+
+impl World {
+    /// Index of the cell owning `rigid_body_handle`.
+    fn cell_of_body(&self, rigid_body_handle: RigidBodyHandle) -> usize {
+        self.rigid_body_set[rigid_body_handle].user_data as usize
+    }
+
+    /// Joins two cells where their surfaces meet so they move as one colony.
+    pub fn bond(&mut self, cell_a: usize, cell_b: usize) -> Option<ImpulseJointHandle> {
+        let body_a = self.cells.get(cell_a)?.as_ref()?.rigid_body_handle;
+        let body_b = self.cells.get(cell_b)?.as_ref()?.rigid_body_handle;
+        let radius_a = self.cells[cell_a].as_ref()?.inner.size();
+        let radius_b = self.cells[cell_b].as_ref()?.inner.size();
+
+        let position_a = self.rigid_body_set[body_a].position();
+        let position_b = self.rigid_body_set[body_b].position();
+        let direction = (position_b.translation.vector - position_a.translation.vector)
+            .try_normalize(f32::EPSILON)?;
+        let anchor_a = position_a.rotation.inverse() * (direction * radius_a);
+        let anchor_b = position_b.rotation.inverse() * (-direction * radius_b);
+
+        let joint = RevoluteJointBuilder::new()
+            .local_anchor1(Point2::from(anchor_a))
+            .local_anchor2(Point2::from(anchor_b));
+
+        Some(self.physics_props.impulse_joint_set.insert(body_a, body_b, joint, true))
+    }
+
+    /// Whether two touching cells would bond: both express enough adhesins,
+    /// they are related and not bonded yet.
+    pub(super) fn bondable(&self, cell_a: usize, cell_b: usize) -> bool {
+        let config = &self.cell_config;
+        let (Some(Some(wrapper_a)), Some(Some(wrapper_b))) = (self.cells.get(cell_a), self.cells.get(cell_b)) else {
+            return false;
+        };
+
+        wrapper_a.inner.is_adhesive(config)
+            && wrapper_b.inner.is_adhesive(config)
+            && wrapper_a
+                .inner
+                .genome
+                .is_related(&wrapper_b.inner.genome, config.kin_distance)
+            && self
+                .physics_props
+                .impulse_joint_set
+                .joints_between(wrapper_a.rigid_body_handle, wrapper_b.rigid_body_handle)
+                .next()
+                .is_none()
+    }
+
+    /// Bonds every pair of touching, related cells that both express enough
+    /// adhesins.
+    pub(super) fn form_bonds(&mut self) {
+        let new_bonds: Vec<(usize, usize)> = self
+            .physics_props
+            .narrow_phase
+            .contact_pairs()
+            .filter(|pair| pair.has_any_active_contact)
+            .filter_map(|pair| {
+                let cell_a = self.collider_set.get(pair.collider1)?.user_data as usize;
+                let cell_b = self.collider_set.get(pair.collider2)?.user_data as usize;
+                // Wrapping around a torus may have moved one of them away
+                // since the contact was found
+                let distance = (self.cell_position(cell_b)? - self.cell_position(cell_a)?).norm();
+                let radii = self.cells[cell_a].as_ref()?.inner.size()
+                    + self.cells[cell_b].as_ref()?.inner.size();
+
+                let touching = distance < radii + self.cell_config.bond_tolerance;
+
+                (touching && self.bondable(cell_a, cell_b)).then_some((cell_a, cell_b))
+            })
+            .collect();
+
+        new_bonds.into_iter().for_each(|(cell_a, cell_b)| {
+            self.bond(cell_a, cell_b);
+        });
+    }
+
+    /// Breaks bonds that carried more than `CellConfig::bond_strength` during
+    /// the last physics step. Bonds of dead cells go with their rigid body.
+    pub(super) fn break_bonds(&mut self) {
+        let max_impulse =
+            self.cell_config.bond_strength * self.physics_props.integration_parameters.dt;
+        let broken: Vec<ImpulseJointHandle> = self
+            .physics_props
+            .impulse_joint_set
+            .iter()
+            .filter(|(_, joint)| joint.impulses.norm() > max_impulse)
+            .map(|(handle, _)| handle)
+            .collect();
+
+        broken.into_iter().for_each(|handle| {
+            self.physics_props.impulse_joint_set.remove(handle, true);
+        });
+    }
+
+    /// Pairs of cell indexes joined by a bond.
+    pub fn bonds(&self) -> Vec<(usize, usize)> {
+        self.physics_props
+            .impulse_joint_set
+            .iter()
+            .map(|(_, joint)| (self.cell_of_body(joint.body1), self.cell_of_body(joint.body2)))
+            .collect()
+    }
+
+    /// Cells directly bonded to `cell_idx`.
+    pub fn bonded_cells(&self, cell_idx: usize) -> Vec<usize> {
+        let Some(Some(cell_wrapper)) = self.cells.get(cell_idx) else {
+            return Vec::new();
+        };
+        let body = cell_wrapper.rigid_body_handle;
+
+        self.physics_props
+            .impulse_joint_set
+            .attached_joints(body)
+            .map(|(body_a, body_b, _, _)| match body_a == body {
+                true => self.cell_of_body(body_b),
+                false => self.cell_of_body(body_a),
+            })
+            .collect()
+    }
+
+    /// Every cell connected to `cell_idx` through bonds, including itself.
+    pub fn colony(&self, cell_idx: usize) -> Vec<usize> {
+        if !matches!(self.cells.get(cell_idx), Some(Some(_))) {
+            return Vec::new();
+        }
+
+        let mut members = HashSet::from([cell_idx]);
+        let mut colony = vec![cell_idx];
+        let mut next = 0;
+        while next < colony.len() {
+            self.bonded_cells(colony[next]).into_iter().for_each(|neighbour| {
+                if members.insert(neighbour) {
+                    colony.push(neighbour);
+                }
+            });
+            next += 1;
+        }
+
+        colony
+    }
+
+    /// Groups of two or more bonded cells.
+    pub fn colonies(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.cells.len()];
+        let mut colonies = Vec::new();
+        self.physics_props
+            .impulse_joint_set
+            .iter()
+            .for_each(|(_, joint)| {
+                let cell_idx = self.cell_of_body(joint.body1);
+                if visited[cell_idx] {
+                    return;
+                }
+                let colony = self.colony(cell_idx);
+                colony.iter().for_each(|&member| visited[member] = true);
+                colonies.push(colony);
+            });
+
+        colonies
+    }
+}
@@ -0,0 +1,35 @@
+use std::f32::consts::PI;
+
+use nalgebra::Vector2;
+
+use super::world::World;
+
+impl World {
+    /// Stokes drag from the medium: damps every cell according to its size
+    /// and `PhysicsPropsStruct::viscosity`, and pushes it towards the local
+    /// flow so that it drifts along at the flow's speed.
+    pub(super) fn apply_drag(&mut self) {
+        let viscosity = self.physics_props.viscosity;
+        let dt = self.physics_props.integration_parameters.dt;
+        let (rigid_body_set, collider_set, environment) =
+            (&mut self.rigid_body_set, &self.collider_set, &self.environment);
+        self.cells.iter().flatten().for_each(|cell_wrapper| {
+            let mass_properties = collider_set[cell_wrapper.collider_handle].mass_properties();
+            let (mass, inertia) = (mass_properties.mass(), mass_properties.principal_inertia());
+            if mass <= 0. || inertia <= 0. {
+                return;
+            }
+            let radius = cell_wrapper.inner.size();
+            let linear_drag = 6. * PI * viscosity * radius;
+            let angular_drag = 8. * PI * viscosity * radius.powi(3);
+
+            let rigid_body = &mut rigid_body_set[cell_wrapper.rigid_body_handle];
+            rigid_body.set_linear_damping(linear_drag / mass);
+            rigid_body.set_angular_damping(angular_drag / inertia);
+            let flow = environment.flow(*rigid_body.translation());
+            if flow != Vector2::zeros() {
+                rigid_body.apply_impulse(flow * linear_drag * dt, true);
+            }
+        });
+    }
+}
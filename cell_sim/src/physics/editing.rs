@@ -0,0 +1,66 @@
+use nalgebra::{vector, Vector2};
+
+use crate::cell::chemicals::Chemicals;
+use crate::cell::death::DeathCause;
+
+use super::world::World;
+
+/// Hand edits to a running world, e.g. from a level editor or a script.
+/// Each keeps the spatial queries up to date, so they also work while the
+/// world is paused.
+impl World {
+    /// Removes every cell overlapping the circle of `radius` around `point`,
+    /// recording a `DeathEvent` with `DeathCause::Removed` for each. Returns
+    /// how many were removed.
+    pub fn remove_cells_within(&mut self, point: Vector2<f32>, radius: f32) -> usize {
+        let cells = self.cells_within(point, radius);
+        cells
+            .iter()
+            .for_each(|&index| self.kill_cell(index, DeathCause::Removed));
+        self.remove_dead_cells();
+        self.refresh_queries();
+
+        cells.len()
+    }
+
+    /// Moves a cell to `position` and stops it, e.g. to drag it around.
+    pub fn move_cell(&mut self, cell_idx: usize, position: Vector2<f32>) {
+        let Some(cell_wrapper) = self.cells.get(cell_idx).and_then(Option::as_ref) else {
+            return;
+        };
+        let rigid_body = &mut self.rigid_body_set[cell_wrapper.rigid_body_handle];
+        rigid_body.set_translation(position, true);
+        rigid_body.set_linvel(vector![0., 0.], true);
+        rigid_body.set_angvel(0., true);
+        // Colliders only follow their body on the next step
+        self.collider_set[cell_wrapper.collider_handle].set_translation(position);
+        self.refresh_queries();
+    }
+
+    /// Deposits `chemicals` into every environment tile whose centre lies
+    /// within `radius` of `point`. Negative amounts take chemicals out.
+    pub fn paint_chemicals(&mut self, point: Vector2<f32>, radius: f32, chemicals: Chemicals) {
+        self.tiles_in_brush(point, radius)
+            .into_iter()
+            .for_each(|index| {
+                let center = self.environment.tile_center(index);
+                self.environment.deposit(center, chemicals);
+            });
+    }
+
+    /// Sets the light level of every environment tile whose centre lies
+    /// within `radius` of `point`.
+    pub fn paint_light(&mut self, point: Vector2<f32>, radius: f32, light: f32) {
+        self.tiles_in_brush(point, radius)
+            .into_iter()
+            .for_each(|index| self.environment.set_light(index, light));
+    }
+
+    fn tiles_in_brush(&self, point: Vector2<f32>, radius: f32) -> Vec<usize> {
+        let extent = vector![radius, radius];
+        self.environment
+            .tiles_within(point - extent, point + extent)
+            .filter(|&index| (self.environment.tile_center(index) - point).norm() <= radius)
+            .collect()
+    }
+}
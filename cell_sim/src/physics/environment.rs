@@ -0,0 +1,245 @@
+use nalgebra::{vector, Vector2};
+
+use crate::cell::chemicals::Chemicals;
+
+/// Chemicals outside of cells, stored on a regular grid of square tiles
+/// covering the world. Positions outside the grid are clamped to the nearest
+/// edge tile.
+pub struct Environment {
+    origin: Vector2<f32>,
+    tile_size: f32,
+    width: usize,
+    height: usize,
+    chemicals: Vec<Chemicals>,
+    /// Light reaching each tile, 1 in the open.
+    light: Vec<f32>,
+    /// Tiles covered by obstacles that chemicals cannot diffuse through.
+    blocked: Vec<bool>,
+    /// Background current in each tile, in world units per second.
+    flow: Vec<Vector2<f32>>,
+    /// Fraction of the difference with each neighbour exchanged per update,
+    /// stable up to 0.25.
+    pub diffusion_rate: f32,
+    /// Fraction of the signalling molecule that breaks down per update.
+    pub signal_decay: f32,
+    /// Whether opposite edges are connected, set from `Topology::Torus`.
+    pub wrap: bool,
+}
+
+impl Environment {
+    pub fn new(origin: Vector2<f32>, size: Vector2<f32>, tile_size: f32) -> Self {
+        debug_assert!(tile_size > 0.);
+        let width = ((size.x / tile_size).ceil() as usize).max(1);
+        let height = ((size.y / tile_size).ceil() as usize).max(1);
+
+        Self {
+            origin,
+            tile_size,
+            width,
+            height,
+            chemicals: vec![Chemicals::default(); width * height],
+            light: vec![1.; width * height],
+            blocked: vec![false; width * height],
+            flow: vec![Vector2::zeros(); width * height],
+            diffusion_rate: 0.1,
+            signal_decay: 0.05,
+            wrap: false,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn tile_size(&self) -> f32 {
+        self.tile_size
+    }
+
+    pub fn origin(&self) -> Vector2<f32> {
+        self.origin
+    }
+
+    pub fn size(&self) -> Vector2<f32> {
+        vector![self.width as f32, self.height as f32] * self.tile_size
+    }
+
+    pub fn tile_index(&self, position: Vector2<f32>) -> usize {
+        let local = (position - self.origin) / self.tile_size;
+        let (x, y) = match self.wrap {
+            true => (
+                local.x.rem_euclid(self.width as f32) as usize % self.width,
+                local.y.rem_euclid(self.height as f32) as usize % self.height,
+            ),
+            false => (
+                (local.x.max(0.) as usize).min(self.width - 1),
+                (local.y.max(0.) as usize).min(self.height - 1),
+            ),
+        };
+
+        y * self.width + x
+    }
+
+    /// Indexes of the tiles overlapping the rectangle from `mins` to `maxs`.
+    pub fn tiles_within(
+        &self,
+        mins: Vector2<f32>,
+        maxs: Vector2<f32>,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let local_mins = (mins - self.origin) / self.tile_size;
+        let local_maxs = (maxs - self.origin) / self.tile_size;
+        let clamp = |value: f32, len: usize| (value.max(0.) as usize).min(len - 1);
+        let (x0, x1) = (
+            clamp(local_mins.x, self.width),
+            clamp(local_maxs.x, self.width),
+        );
+        let (y0, y1) = (
+            clamp(local_mins.y, self.height),
+            clamp(local_maxs.y, self.height),
+        );
+
+        (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| y * self.width + x))
+    }
+
+    pub fn tile_center(&self, index: usize) -> Vector2<f32> {
+        let (x, y) = (index % self.width, index / self.width);
+        self.origin + (vector![x as f32, y as f32] + vector![0.5, 0.5]) * self.tile_size
+    }
+
+    pub fn tiles(&self) -> &[Chemicals] {
+        &self.chemicals
+    }
+
+    pub fn get(&self, position: Vector2<f32>) -> &Chemicals {
+        &self.chemicals[self.tile_index(position)]
+    }
+
+    pub fn get_mut(&mut self, position: Vector2<f32>) -> &mut Chemicals {
+        let index = self.tile_index(position);
+        &mut self.chemicals[index]
+    }
+
+    pub fn light(&self, position: Vector2<f32>) -> f32 {
+        self.light[self.tile_index(position)]
+    }
+
+    pub fn light_map(&self) -> &[f32] {
+        &self.light
+    }
+
+    pub fn set_light(&mut self, index: usize, light: f32) {
+        self.light[index] = light;
+    }
+
+    pub fn block_light(&mut self, index: usize) {
+        self.light[index] = 0.;
+    }
+
+    pub fn is_blocked(&self, index: usize) -> bool {
+        self.blocked[index]
+    }
+
+    pub fn block_diffusion(&mut self, index: usize) {
+        self.blocked[index] = true;
+    }
+
+    pub fn flow(&self, position: Vector2<f32>) -> Vector2<f32> {
+        self.flow[self.tile_index(position)]
+    }
+
+    pub fn flow_map(&self) -> &[Vector2<f32>] {
+        &self.flow
+    }
+
+    pub fn set_flow(&mut self, index: usize, velocity: Vector2<f32>) {
+        self.flow[index] = velocity;
+    }
+
+    /// Adds `chemicals` to the tile at `position`. Negative amounts are taken
+    /// out, but never past zero.
+    pub fn deposit(&mut self, position: Vector2<f32>, chemicals: Chemicals) {
+        let tile = self.get_mut(position);
+        *tile += chemicals;
+        tile.clamp_non_negative();
+    }
+
+    /// Spreads chemicals between neighbouring tiles. Blocked tiles neither
+    /// give nor receive anything.
+    pub fn diffuse(&mut self) {
+        let previous = self.chemicals.clone();
+        let (width, height, wrap) = (self.width, self.height, self.wrap);
+        let blocked = &self.blocked;
+        self.chemicals
+            .iter_mut()
+            .enumerate()
+            .filter(|(index, _)| !blocked[*index])
+            .for_each(|(index, tile)| {
+                let here = previous[index];
+                let mut flux = Chemicals::default();
+                neighbours(index, width, height, wrap)
+                    .into_iter()
+                    .flatten()
+                    .filter(|&neighbour| !blocked[neighbour])
+                    .for_each(|neighbour| {
+                        flux += previous[neighbour] - here;
+                    });
+                *tile += flux * self.diffusion_rate;
+                tile.signal *= 1. - self.signal_decay;
+            });
+    }
+
+    /// Carries chemicals downstream with the flow over `dt` seconds, moving a
+    /// share of each tile into its downstream neighbours. Nothing flows into
+    /// or out of blocked tiles.
+    pub fn advect(&mut self, dt: f32) {
+        if self.flow.iter().all(|velocity| *velocity == Vector2::zeros()) {
+            return;
+        }
+
+        let mut next = self.chemicals.clone();
+        let (width, height, wrap) = (self.width, self.height, self.wrap);
+        (0..self.chemicals.len())
+            .filter(|&index| !self.blocked[index])
+            .for_each(|index| {
+                let courant = self.flow[index] * dt / self.tile_size;
+                // Never move more than the whole tile
+                let scale = 1. / (courant.x.abs() + courant.y.abs()).max(1.);
+                let [left, right, down, up] = neighbours(index, width, height, wrap);
+                [
+                    (courant.x.abs(), if courant.x > 0. { right } else { left }),
+                    (courant.y.abs(), if courant.y > 0. { up } else { down }),
+                ]
+                .into_iter()
+                .for_each(|(fraction, target)| {
+                    if let Some(target) = target.filter(|&target| !self.blocked[target]) {
+                        let moved = self.chemicals[index] * (fraction * scale);
+                        next[index] -= moved;
+                        next[target] += moved;
+                    }
+                });
+            });
+
+        self.chemicals = next;
+    }
+}
+
+/// Left, right, lower and upper neighbours of a tile, `None` past an edge
+/// unless the grid wraps.
+fn neighbours(index: usize, width: usize, height: usize, wrap: bool) -> [Option<usize>; 4] {
+    let (x, y) = (index % width, index / width);
+    [
+        (x > 0 || wrap).then(|| (x + width - 1) % width + y * width),
+        (x + 1 < width || wrap).then(|| (x + 1) % width + y * width),
+        (y > 0 || wrap).then(|| x + (y + height - 1) % height * width),
+        (y + 1 < height || wrap).then(|| x + (y + 1) % height * width),
+    ]
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new(vector![0., 0.], vector![10000., 1200.], 50.)
+    }
+}
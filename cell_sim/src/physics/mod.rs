@@ -1,18 +1,38 @@
 mod cell_wrapper;
+mod colony;
+mod drag;
+mod editing;
+mod environment;
+mod obstacle;
 mod physics_props;
+mod query;
+mod scenario;
+mod scheduler;
+mod statistics;
+mod topology;
 mod world;
 mod updates;
-pub use world::World;
+pub use environment::Environment;
+pub use obstacle::{Obstacle, ObstacleShape};
+pub use scenario::{Scenario, ScenarioError};
+pub use scheduler::{Scheduler, MIN_DT};
+pub use statistics::Statistics;
+pub use topology::Topology;
+pub use world::{BirthEvent, DeathEvent, World};
 pub use updates::*;
 
 #[cfg(test)]
 mod tests {
     use nalgebra::vector;
 
+    use crate::cell::chemicals::Chemicals;
+    use crate::cell::component::{ComponentProps, COMPONENT_COUNT};
+    use crate::cell::death::DeathCause;
+    use crate::cell::genetics::genome::{Cue, Gene, Genome, REGULATOR_COUNT};
     use crate::cell::Cell;
 
-    use super::World;
-    use super::updates::{update_physics, update_cells};
+    use super::{Obstacle, ObstacleShape, Scenario, Topology, World, MIN_DT};
+    use super::updates::{share_resources, update_physics, update_cells};
 
     #[test]
     fn test_phsyics() {
@@ -38,7 +58,408 @@ mod tests {
         });
 
         (0..250).for_each(|_| {
-            update_cells(&mut world.cells);
+            update_cells(
+                &mut world.cells,
+                &world.cell_config,
+                world.scheduler.chemistry_steps,
+                world.scheduler.chemistry_dt,
+            );
         })
     }
+
+    #[test]
+    fn test_death() {
+        let mut world = World::default();
+        let mut cell = Cell::new_random();
+        cell.inner.chemicals.glucose = 5.;
+        cell.inner.proteins = 3.;
+        let index = world.add_cell(cell, vector![100., 100.]);
+        world.kill_cell(index, DeathCause::Predation);
+        world.update();
+
+        assert!(world.cells[index].is_none());
+        assert_eq!(world.rigid_body_set.len(), 0);
+        assert_eq!(world.death_events.len(), 1);
+        assert_eq!(world.death_events[0].cause, DeathCause::Predation);
+        // Proteins and nucleotides come back as glucose
+        let glucose: f32 = world.environment.tiles().iter().map(|tile| tile.glucose).sum();
+        assert!(glucose > 5.);
+        assert!((glucose - cell.remains().glucose).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_adhesion() {
+        let mut world = World::default();
+        let mut cell = Cell::new_random();
+        cell.inner.chemicals.atp = 1000.;
+        cell.genome = Genome::default();
+        cell.components = [Some(ComponentProps::default()); COMPONENT_COUNT];
+        // Flagella would push the pair apart before they touch
+        cell.components[0] = None;
+        let radius = cell.size();
+        let a = world.add_cell(cell, vector![100., 100.]);
+        let b = world.add_cell(cell, vector![100. + radius * 1.5, 100.]);
+        let mut loner = Cell::new_random();
+        loner.inner.chemicals.atp = 1000.;
+        let c = world.add_cell(loner, vector![500., 500.]);
+
+        world.update();
+        world.update();
+
+        assert_eq!(world.bonded_cells(a), vec![b]);
+        let mut colony = world.colony(b);
+        colony.sort();
+        assert_eq!(colony, vec![a, b]);
+        assert_eq!(world.colonies().len(), 1);
+        assert_eq!(world.colony(c), vec![c]);
+
+        world.kill_cell(a, DeathCause::Predation);
+        world.update();
+        assert!(world.bonded_cells(b).is_empty());
+    }
+
+    #[test]
+    fn test_bond_distance() {
+        let mut world = World::default();
+        let mut cell = Cell::new_random();
+        cell.genome = Genome::default();
+        cell.adhesion = 1.;
+        let radius = cell.size();
+        let a = world.add_cell(cell, vector![100., 100.]);
+        let b = world.add_cell(cell, vector![100. + radius * 1.5, 100.]);
+        let place = |world: &mut World, x: f32| {
+            let rigid_body_handle = world.cells[b].as_ref().unwrap().rigid_body_handle;
+            world.rigid_body_set[rigid_body_handle].set_translation(vector![x, 100.], true);
+        };
+
+        // The contact is still there after they were pulled apart
+        update_physics(&mut world.physics_props, &mut world.rigid_body_set, &mut world.collider_set);
+        let gap = world.cell_config.bond_tolerance * 2.;
+        place(&mut world, 100. + radius * 2. + gap);
+        world.form_bonds();
+        assert!(world.bonded_cells(a).is_empty());
+
+        place(&mut world, 100. + radius * 1.5);
+        world.form_bonds();
+        assert_eq!(world.bonded_cells(a), vec![b]);
+    }
+
+    #[test]
+    fn test_sharing() {
+        let mut world = World::default();
+        let mut rich = Cell::new_random();
+        rich.inner.chemicals.atp = 10.;
+        rich.inner.water = 1.;
+        let mut poor = rich;
+        poor.inner.chemicals.atp = 0.;
+        let a = world.add_cell(rich, vector![0., 0.]);
+        let b = world.add_cell(poor, vector![10., 0.]);
+
+        world.cell_config.sharing_rate = 1.;
+        share_resources(&mut world.cells, &[(a, b)], &world.cell_config);
+
+        let atp = |idx: usize| world.cells[idx].as_ref().unwrap().inner.inner.chemicals.atp;
+        assert!((atp(a) - 5.).abs() < 1e-4);
+        assert!((atp(b) - 5.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_sharing_conserves() {
+        let mut world = World::default();
+        let mut hub = Cell::new_random();
+        hub.inner.chemicals.atp = 10.;
+        hub.inner.water = 1.;
+        let mut empty = hub;
+        empty.inner.chemicals.atp = 0.;
+        let hub = world.add_cell(hub, vector![0., 0.]);
+        let bonds: Vec<(usize, usize)> = (0..6)
+            .map(|i| (hub, world.add_cell(empty, vector![10. * (i + 1) as f32, 0.])))
+            .collect();
+
+        let total = |world: &World| -> f32 {
+            world.cells.iter().flatten().map(|cell_wrapper| cell_wrapper.inner.inner.chemicals.atp).sum()
+        };
+        world.cell_config.sharing_rate = 1.;
+        share_resources(&mut world.cells, &bonds, &world.cell_config);
+
+        assert!((total(&world) - 10.).abs() < 1e-4);
+        let hub_atp = world.cells[hub].as_ref().unwrap().inner.inner.chemicals.atp;
+        assert!(hub_atp >= 0.);
+    }
+
+    #[test]
+    fn test_division() {
+        let mut world = World::default();
+        let mut cell = Cell::new_random();
+        cell.inner.chemicals.atp = 1000.;
+        cell.inner.nucleotides = world.cell_config.division_nucleotides * 2.;
+        cell.inner.proteins = world.cell_config.division_proteins * 4.;
+        cell.components = [Some(ComponentProps::default()); COMPONENT_COUNT];
+        // Component 0 is present but never expressed
+        cell.components[0].as_mut().unwrap().expression = 0.;
+        cell.genome.programs.iter_mut().for_each(|program| {
+            program.genes[0] = Gene { basal: 0., regulators: [None; REGULATOR_COUNT] };
+        });
+        cell.genome.cues[1] = None;
+        cell.genome.cues[2] = Some(Cue::Bonds(0));
+        let parent = world.add_cell(cell, vector![100., 100.]);
+        world.update();
+
+        assert_eq!(world.birth_events.len(), 1);
+        assert_eq!(world.birth_events[0].parent, parent);
+        let daughter = world.birth_events[0].index;
+        let (parent, daughter) = (world.cells[parent].as_ref().unwrap(), world.cells[daughter].as_ref().unwrap());
+        assert_eq!(daughter.inner.cell_type, 2);
+        assert_eq!(daughter.inner.generation, 1);
+        assert_eq!(daughter.parent, Some(parent.id));
+        assert_eq!(daughter.founder, parent.id);
+        assert_eq!(world.statistics.population, 2);
+        assert_eq!(world.statistics.cell_types[2], 1);
+        (0..COMPONENT_COUNT).for_each(|i| {
+            let expression = parent.inner.components[i].unwrap().expression + daughter.inner.components[i].unwrap().expression;
+            assert!((world.statistics.components[i] - expression).abs() < 1e-6);
+        });
+        assert_eq!(world.statistics.components[0], 0.);
+        assert_eq!(world.statistics.birth_rate(), 1.);
+    }
+
+    #[test]
+    fn test_topology() {
+        let mut world = World::default();
+        let size = world.environment.size();
+        world.set_topology(Topology::Torus);
+        let mut cell = Cell::new_random();
+        cell.inner.chemicals.atp = 1000.;
+        let index = world.add_cell(cell, vector![size.x + 10., -10.]);
+        world.update();
+        let position = world.cell_position(index).unwrap();
+        assert!(position.x >= 0. && position.x < size.x);
+        assert!(position.y >= 0. && position.y < size.y);
+        let across = world.displacement(vector![1., 1.], vector![size.x - 1., 1.]);
+        assert!((across.x + 2.).abs() < 1e-3);
+
+        world.set_topology(Topology::Walls);
+        assert_eq!(world.rigid_body_set.len(), 2);
+
+        world.set_topology(Topology::Open {
+            despawn_radius: Some(1.),
+        });
+        assert_eq!(world.rigid_body_set.len(), 1);
+        world.update();
+        assert!(world.cells[index].is_none());
+        assert_eq!(world.death_events[0].cause, DeathCause::OutOfBounds);
+    }
+
+    #[test]
+    fn test_torus() {
+        let mut world = World::default();
+        world.set_topology(Topology::Torus);
+        let size = world.environment.size();
+        let mut cell = Cell::new_random();
+        cell.inner.chemicals.atp = 1000.;
+        cell.components = [None; COMPONENT_COUNT];
+        let radius = cell.size();
+        // Overlapping across the left edge, the point only lies in `right`
+        let left = world.add_cell(cell, vector![radius * 1.2, 500.]);
+        let right = world.add_cell(cell, vector![size.x - radius * 0.5, 500.]);
+        world.refresh_queries();
+        let point = vector![radius * 0.1, 500.];
+        assert_eq!(world.cell_at(point), Some(right));
+        assert_eq!(world.cells_within(point, 0.), vec![right]);
+        assert_eq!(world.nearest(point, 1), vec![right]);
+
+        let distance = |world: &World| {
+            world.displacement(world.cell_position(left).unwrap(), world.cell_position(right).unwrap()).norm()
+        };
+        let start = distance(&world);
+        (0..10).for_each(|_| world.update());
+        assert!(distance(&world) > start);
+
+        // Adhesive relatives bond across the edge and wrap as one colony
+        let mut world = World::default();
+        world.set_topology(Topology::Torus);
+        cell.genome = Genome::default();
+        cell.components = [Some(ComponentProps::default()); COMPONENT_COUNT];
+        cell.components[0] = None;
+        let radius = cell.size();
+        let left = world.add_cell(cell, vector![radius * 0.5, 500.]);
+        let right = world.add_cell(cell, vector![size.x - radius * 0.5, 500.]);
+        world.refresh_queries();
+        world.update();
+        world.update();
+        assert_eq!(world.bonded_cells(left), vec![right]);
+        (0..20).for_each(|_| {
+            world.update();
+            let (a, b) = (world.cell_position(left).unwrap(), world.cell_position(right).unwrap());
+            assert!((a - b).norm() < radius * 3.);
+            assert!([a, b].iter().all(|position| position.x > -radius * 10. && position.x < size.x + radius * 10.));
+        });
+    }
+
+    #[test]
+    fn test_scenario() {
+        let scenario = Scenario::parse(
+            "# a shaded rock in a walled box\n\
+             topology walls\n\
+             circle 500 500 100 blocks_light blocks_diffusion\n\
+             polyline 0 0 100 100\n",
+        )
+        .unwrap();
+        assert_eq!(scenario.topology, Some(Topology::Walls));
+        assert_eq!(scenario.obstacles.len(), 2);
+        assert!(Scenario::parse("circle 1 2").is_err());
+
+        let mut world = World::default();
+        world.apply_scenario(&scenario);
+        let environment = &world.environment;
+        assert_eq!(environment.light(vector![500., 500.]), 0.);
+        assert_eq!(environment.light(vector![800., 500.]), 1.);
+
+        world.environment.get_mut(vector![500., 500.]).glucose = 10.;
+        world.environment.diffuse();
+        assert_eq!(world.environment.get(vector![500., 500.]).glucose, 10.);
+        assert_eq!(world.environment.get(vector![500., 650.]).glucose, 0.);
+    }
+
+    #[test]
+    fn test_mask() {
+        let directory = std::env::temp_dir().join(format!("cell_sim_mask_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        // The top row of the image is the top of the mask
+        std::fs::write(directory.join("plain.pbm"), "P1\n# tiny\n3 2\n1 1 0\n0 0 1\n").unwrap();
+        std::fs::write(directory.join("raw#1.pbm"), b"P4 3 2\n\xc0\x20").unwrap();
+        std::fs::write(
+            directory.join("scenario.txt"),
+            "# masks\nmask plain.pbm 10 100 0 blocks_light\nmask raw#1.pbm 10 # raw\n",
+        )
+        .unwrap();
+        let scenario = Scenario::load(directory.join("scenario.txt")).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let shapes: Vec<ObstacleShape> = scenario.obstacles.iter().map(|obstacle| obstacle.shape.clone()).collect();
+        assert_eq!(shapes, vec![
+            ObstacleShape::Rectangles(vec![(vector![120., 0.], vector![130., 10.]), (vector![100., 10.], vector![120., 20.])]),
+            ObstacleShape::Rectangles(vec![(vector![20., 0.], vector![30., 10.]), (vector![0., 10.], vector![20., 20.])]),
+        ]);
+        assert!(scenario.obstacles[0].blocks_light);
+        assert!(!scenario.obstacles[1].blocks_light);
+
+        // Each mask is a single fixed body
+        let mut world = World::default();
+        world.apply_scenario(&scenario);
+        assert_eq!(world.rigid_body_set.len(), 2);
+        assert_eq!(world.environment.light(vector![125., 5.]), 0.);
+
+        assert!(Scenario::parse("mask missing.pbm 10").is_err());
+        assert!(Scenario::parse("mask").is_err());
+        assert!(Scenario::parse("polygon 0 0 10 10 0 0").is_err());
+        world.add_obstacle(&Obstacle::new(ObstacleShape::Polygon(vec![vector![0., 0.]; 3])));
+        assert_eq!(world.rigid_body_set.len(), 2);
+    }
+
+    #[test]
+    fn test_drag() {
+        let mut world = World::default();
+        let mut cell = Cell::new_random();
+        cell.components = [None; COMPONENT_COUNT];
+        let index = world.add_cell(cell, vector![500., 500.]);
+        let start = world.cell_position(index).unwrap();
+        let flow = vector![20., 0.];
+        (0..world.environment.tiles().len()).for_each(|tile| world.environment.set_flow(tile, flow));
+        world.environment.get_mut(vector![500., 500.]).glucose = 100.;
+
+        (0..200).for_each(|_| world.update());
+        let velocity = *world.rigid_body_set[world.cells[index].as_ref().unwrap().rigid_body_handle].linvel();
+        assert!((velocity - flow).norm() < 1.);
+        assert!(world.cell_position(index).unwrap().x > start.x + 30.);
+        assert!(world.environment.get(vector![500., 500.]).glucose < 50.);
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut world = World::default();
+        let mut cell = Cell::new_random();
+        cell.components = [None; COMPONENT_COUNT];
+        let radius = cell.size();
+        let near = world.add_cell(cell, vector![100., 100.]);
+        let far = world.add_cell(cell, vector![100. + radius * 10., 100.]);
+        world.refresh_queries();
+
+        assert_eq!(world.cell_at(vector![100., 100.]), Some(near));
+        assert_eq!(world.cell_at(vector![100., 100. + radius * 2.]), None);
+        assert_eq!(world.cells_within(vector![100., 100.], radius), vec![near]);
+        assert_eq!(world.nearest(vector![100. + radius * 8., 100.], 2), vec![far, near]);
+        let hit = world.cast_ray(vector![100. + radius * 5., 100.], vector![1., 0.], 1000.);
+        assert_eq!(hit.map(|(cell_idx, _)| cell_idx), Some(far));
+        assert!((hit.unwrap().1 - radius * 4.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut world = World::default();
+        let mut cell = Cell::new_random();
+        cell.components = [None; COMPONENT_COUNT];
+        let radius = cell.size();
+        let a = world.add_cell(cell, vector![100., 100.]);
+        let b = world.add_cell(cell, vector![100. + radius * 10., 100.]);
+        world.refresh_queries();
+
+        world.move_cell(b, vector![300., 300.]);
+        assert_eq!(world.cell_at(vector![300., 300.]), Some(b));
+        assert_eq!(world.remove_cells_within(vector![100., 100.], radius), 1);
+        assert!(world.cells[a].is_none());
+        assert_eq!(world.death_events[0].cause, DeathCause::Removed);
+        assert_eq!(world.cell_at(vector![100., 100.]), None);
+
+        let glucose = world.environment.get(vector![500., 500.]).glucose;
+        let chemicals = Chemicals { glucose: 1., ..Default::default() };
+        world.paint_chemicals(vector![500., 500.], world.environment.tile_size(), chemicals);
+        assert_eq!(world.environment.get(vector![500., 500.]).glucose, glucose + 1.);
+        world.paint_light(vector![500., 500.], world.environment.tile_size(), 0.25);
+        assert_eq!(world.environment.light(vector![500., 500.]), 0.25);
+    }
+
+    #[test]
+    fn test_scheduler() {
+        let mut world = World::default();
+        world.scheduler.physics_dt = 0.1;
+        world.scheduler.chemistry_dt = 0.2;
+        world.scheduler.chemistry_steps = 1;
+        let mut cell = Cell::new_random();
+        cell.components = [None; COMPONENT_COUNT];
+        let index = world.add_cell(cell, vector![100., 100.]);
+        let age = |world: &World| world.cells[index].as_ref().unwrap().inner.age;
+
+        // Age follows the chemistry updates, one so far
+        world.advance(0.35);
+        assert!((world.time() - 0.3).abs() < 1e-6);
+        assert_eq!(age(&world), 0.2);
+
+        world.scheduler.paused = true;
+        world.advance(1.);
+        assert!((world.time() - 0.3).abs() < 1e-6);
+        world.update();
+        assert!((world.time() - 0.4).abs() < 1e-6);
+        assert_eq!(age(&world), 0.4);
+
+        world.scheduler.paused = false;
+        world.scheduler.speed = 2.;
+        world.advance(0.25);
+        assert!((world.time() - 0.9).abs() < 1e-6);
+        assert_eq!(world.scheduler.ticks(), 9);
+
+        let steps = world.run_for(std::time::Duration::ZERO);
+        assert_eq!(steps, 1);
+        assert_eq!(world.scheduler.ticks(), 10);
+
+        // Invalid steps are raised instead of making the work unbounded
+        world.scheduler.physics_dt = 0.;
+        world.scheduler.chemistry_dt = f32::NAN;
+        world.advance(1.);
+        assert_eq!(world.scheduler.ticks(), 10 + world.scheduler.max_steps as u64);
+        assert_eq!(world.scheduler.physics_dt, MIN_DT);
+        world.scheduler.physics_dt = 1.;
+        world.scheduler.chemistry_dt = 0.;
+        assert_eq!(world.scheduler.step(), world.scheduler.max_chemistry_updates);
+    }
 }
@@ -0,0 +1,170 @@
+use nalgebra::{point, vector, Isometry2, Point2, Vector2};
+use rapier2d::dynamics::RigidBodyBuilder;
+use rapier2d::geometry::{Collider, ColliderBuilder, SharedShape};
+
+use super::world::{World, NOT_A_CELL};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObstacleShape {
+    Circle {
+        center: Vector2<f32>,
+        radius: f32,
+    },
+    /// Closed, filled outline. Does not need to be convex.
+    Polygon(Vec<Vector2<f32>>),
+    /// Open chain of thin walls.
+    Polyline(Vec<Vector2<f32>>),
+    /// Axis-aligned boxes, each given by its lower left and upper right
+    /// corners, sharing one collider.
+    Rectangles(Vec<(Vector2<f32>, Vector2<f32>)>),
+}
+
+impl ObstacleShape {
+    /// Whether no collider can be built from the shape, e.g. a polygon with
+    /// fewer than 3 distinct points.
+    pub fn is_degenerate(&self) -> bool {
+        let distinct = |vertices: &[Vector2<f32>]| {
+            (0..vertices.len())
+                .filter(|&i| !vertices[..i].contains(&vertices[i]))
+                .count()
+        };
+        match self {
+            ObstacleShape::Circle { radius, .. } => *radius <= 0.,
+            ObstacleShape::Polygon(vertices) => distinct(vertices) < 3,
+            ObstacleShape::Polyline(vertices) => distinct(vertices) < 2,
+            ObstacleShape::Rectangles(rectangles) => rectangles.is_empty(),
+        }
+    }
+}
+
+/// Static geometry cells collide with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Obstacle {
+    pub shape: ObstacleShape,
+    /// Environment tiles covered by the obstacle receive no light.
+    pub blocks_light: bool,
+    /// Environment tiles covered by the obstacle take no part in diffusion.
+    pub blocks_diffusion: bool,
+}
+
+impl Obstacle {
+    pub fn new(shape: ObstacleShape) -> Self {
+        Self {
+            shape,
+            blocks_light: false,
+            blocks_diffusion: false,
+        }
+    }
+
+    /// Obstacle made of one rectangle per horizontal run of `true` pixels in
+    /// a row-major `mask`, each pixel covering `pixel_size` world units from
+    /// `origin`. `None` if no pixel is set.
+    pub fn from_mask(
+        mask: &[bool],
+        width: usize,
+        origin: Vector2<f32>,
+        pixel_size: f32,
+    ) -> Option<Obstacle> {
+        let mut rectangles = Vec::new();
+        mask.chunks(width).enumerate().for_each(|(y, row)| {
+            let mut x = 0;
+            while x < row.len() {
+                if !row[x] {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < row.len() && row[x] {
+                    x += 1;
+                }
+                let (left, right) = (start as f32 * pixel_size, x as f32 * pixel_size);
+                let (bottom, top) = (y as f32 * pixel_size, (y + 1) as f32 * pixel_size);
+                rectangles.push((origin + vector![left, bottom], origin + vector![right, top]));
+            }
+        });
+
+        (!rectangles.is_empty()).then(|| Obstacle::new(ObstacleShape::Rectangles(rectangles)))
+    }
+
+    fn collider(&self) -> Option<Collider> {
+        if self.shape.is_degenerate() {
+            return None;
+        }
+        let builder = match &self.shape {
+            ObstacleShape::Circle { center, radius } => {
+                ColliderBuilder::ball(*radius).translation(*center)
+            }
+            ObstacleShape::Polygon(vertices) => {
+                let points: Vec<Point2<f32>> = vertices.iter().map(|&v| Point2::from(v)).collect();
+                let count = points.len() as u32;
+                let indices: Vec<[u32; 2]> = (0..count).map(|i| [i, (i + 1) % count]).collect();
+                ColliderBuilder::convex_decomposition(&points, &indices)
+            }
+            ObstacleShape::Polyline(vertices) => {
+                let points = vertices.iter().map(|&v| Point2::from(v)).collect();
+                ColliderBuilder::polyline(points, None)
+            }
+            ObstacleShape::Rectangles(rectangles) => {
+                let shapes = rectangles
+                    .iter()
+                    .map(|&(min, max)| {
+                        let (center, half_extents) = ((min + max) / 2., (max - min) / 2.);
+                        (
+                            Isometry2::translation(center.x, center.y),
+                            SharedShape::cuboid(half_extents.x, half_extents.y),
+                        )
+                    })
+                    .collect();
+                ColliderBuilder::compound(shapes)
+            }
+        };
+
+        Some(builder.user_data(NOT_A_CELL).build())
+    }
+}
+
+impl World {
+    /// Adds `obstacle` as a fixed collider and marks the environment tiles it
+    /// covers. Degenerate obstacles are ignored.
+    pub fn add_obstacle(&mut self, obstacle: &Obstacle) {
+        let Some(collider) = obstacle.collider() else {
+            return;
+        };
+        let body = self
+            .rigid_body_set
+            .insert(RigidBodyBuilder::fixed().user_data(NOT_A_CELL).build());
+        let collider_handle = self.collider_set.insert_with_parent(
+            collider,
+            body,
+            &mut self.rigid_body_set,
+        );
+
+        if !obstacle.blocks_light && !obstacle.blocks_diffusion {
+            return;
+        }
+        let collider = &self.collider_set[collider_handle];
+        let aabb = collider.compute_aabb();
+        let reach = self.environment.tile_size() / 2.;
+        let covered: Vec<usize> = self
+            .environment
+            .tiles_within(aabb.mins.coords, aabb.maxs.coords)
+            .filter(|&index| {
+                let center = self.environment.tile_center(index);
+                collider.shape().distance_to_point(
+                    collider.position(),
+                    &point![center.x, center.y],
+                    true,
+                ) <= reach
+            })
+            .collect();
+
+        covered.into_iter().for_each(|index| {
+            if obstacle.blocks_light {
+                self.environment.block_light(index);
+            }
+            if obstacle.blocks_diffusion {
+                self.environment.block_diffusion(index);
+            }
+        });
+    }
+}
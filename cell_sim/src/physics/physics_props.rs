@@ -11,6 +11,9 @@ pub struct PhysicsPropsStruct {
     pub impulse_joint_set: ImpulseJointSet,
     pub multibody_joint_set: MultibodyJointSet,
     pub ccd_solver: CCDSolver,
+    pub query_pipeline: QueryPipeline,
+    /// Viscosity of the medium cells swim in, scaling their drag.
+    pub viscosity: f32,
 }
 
 impl Default for PhysicsPropsStruct {
@@ -25,6 +28,8 @@ impl Default for PhysicsPropsStruct {
             impulse_joint_set: ImpulseJointSet::new(),
             multibody_joint_set: MultibodyJointSet::new(),
             ccd_solver: CCDSolver::new(),
+            query_pipeline: QueryPipeline::new(),
+            viscosity: 1.,
         }
     }
 }
@@ -0,0 +1,164 @@
+use nalgebra::{vector, Point2, Vector2};
+use rapier2d::geometry::{Ball, ColliderHandle, Ray};
+use rapier2d::math::Isometry;
+use rapier2d::pipeline::QueryFilter;
+
+use super::topology::Topology;
+use super::world::{World, NOT_A_CELL};
+
+/// Spatial queries over living cells, answered with cell indexes. They see
+/// the world as it was at the end of the last `World::update`; call
+/// `World::refresh_queries` after adding or moving cells by hand. On a torus
+/// they also find cells across the edges.
+impl World {
+    pub fn refresh_queries(&mut self) {
+        self.physics_props
+            .query_pipeline
+            .update(&self.rigid_body_set, &self.collider_set);
+        self.overhang = match self.topology {
+            Topology::Torus => {
+                let (origin, size) = (self.environment.origin(), self.environment.size());
+                self.cells
+                    .iter()
+                    .flatten()
+                    .filter_map(|cell_wrapper| {
+                        let position = self.cell_position(cell_wrapper.index)? - origin;
+                        let radius = cell_wrapper.inner.size();
+                        let below = -(position - vector![radius, radius]);
+                        let above = position + vector![radius, radius] - size;
+                        Some(below.max().max(above.max()))
+                    })
+                    .fold(0., f32::max)
+            }
+            _ => 0.,
+        };
+    }
+
+    fn cell_of_collider(&self, collider: ColliderHandle) -> Option<usize> {
+        let index = self.collider_set.get(collider)?.user_data;
+        (index != NOT_A_CELL).then_some(index as usize)
+    }
+
+    /// `point` and, on a torus, its copies a whole world away that a circle
+    /// of `radius` around them could find cells at. Cells wrap as colonies,
+    /// so they may sit up to `overhang` outside the edges.
+    pub(super) fn images(
+        &self,
+        point: Vector2<f32>,
+        radius: f32,
+    ) -> impl Iterator<Item = Vector2<f32>> {
+        let size = self.environment.size();
+        let local = point - self.environment.origin();
+        let shifts = |local: f32, size: f32| match self.topology {
+            Topology::Torus => {
+                let reach = radius + self.overhang;
+                let first = ((-self.overhang - reach - local) / size).ceil() as i32;
+                let last = ((size + self.overhang + reach - local) / size).floor() as i32;
+                first..=last
+            }
+            _ => 0..=0,
+        };
+        let (xs, ys) = (shifts(local.x, size.x), shifts(local.y, size.y));
+        xs.flat_map(move |x| {
+            ys.clone()
+                .map(move |y| point + vector![x as f32 * size.x, y as f32 * size.y])
+        })
+    }
+
+    /// Cells overlapping the circle of `radius` around `point`, ignoring the
+    /// topology.
+    pub(super) fn cells_overlapping(&self, point: Vector2<f32>, radius: f32) -> Vec<usize> {
+        let mut cells = Vec::new();
+        self.physics_props.query_pipeline.intersections_with_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &Isometry::translation(point.x, point.y),
+            &Ball::new(radius),
+            QueryFilter::only_dynamic(),
+            |collider| {
+                cells.extend(self.cell_of_collider(collider));
+                true
+            },
+        );
+
+        cells
+    }
+
+    /// Cells overlapping the circle of `radius` around `point`.
+    pub fn cells_within(&self, point: Vector2<f32>, radius: f32) -> Vec<usize> {
+        let mut cells: Vec<usize> = self
+            .images(point, radius)
+            .flat_map(|image| self.cells_overlapping(image, radius))
+            .collect();
+        cells.sort_unstable();
+        cells.dedup();
+
+        cells
+    }
+
+    /// Up to `k` cells, closest centre first.
+    pub fn nearest(&self, point: Vector2<f32>, k: usize) -> Vec<usize> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let limit = self.environment.size().norm() * 2.;
+        let mut radius = self.environment.tile_size();
+        let mut cells = self.cells_within(point, radius);
+        while cells.len() < k && radius < limit {
+            radius *= 2.;
+            cells = self.cells_within(point, radius);
+        }
+
+        let distance = |cell_idx: &usize| {
+            self.cell_position(*cell_idx)
+                .map_or(f32::INFINITY, |position| {
+                    self.displacement(point, position).norm()
+                })
+        };
+        cells.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        cells.truncate(k);
+
+        cells
+    }
+
+    /// The cell covering `point`, if any.
+    pub fn cell_at(&self, point: Vector2<f32>) -> Option<usize> {
+        self.images(point, 0.).find_map(|image| {
+            let mut found = None;
+            self.physics_props.query_pipeline.intersections_with_point(
+                &self.rigid_body_set,
+                &self.collider_set,
+                &Point2::from(image),
+                QueryFilter::only_dynamic(),
+                |collider| {
+                    found = self.cell_of_collider(collider);
+                    found.is_none()
+                },
+            );
+
+            found
+        })
+    }
+
+    /// First cell hit by a ray from `origin` along `direction`, with the
+    /// distance to it. Obstacles and walls stop the ray.
+    pub fn cast_ray(
+        &self,
+        origin: Vector2<f32>,
+        direction: Vector2<f32>,
+        max_distance: f32,
+    ) -> Option<(usize, f32)> {
+        let direction = direction.try_normalize(f32::EPSILON)?;
+        let (collider, distance) = self.physics_props.query_pipeline.cast_ray(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &Ray::new(Point2::from(origin), direction),
+            max_distance,
+            true,
+            QueryFilter::default(),
+        )?;
+
+        Some((self.cell_of_collider(collider)?, distance))
+    }
+}
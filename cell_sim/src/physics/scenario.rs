@@ -0,0 +1,305 @@
+use std::fmt;
+use std::path::Path;
+
+use nalgebra::{vector, Vector2};
+
+use super::obstacle::{Obstacle, ObstacleShape};
+use super::topology::Topology;
+use super::world::World;
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(error) => write!(f, "could not read scenario: {error}"),
+            ScenarioError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScenarioError::Io(error) => Some(error),
+            ScenarioError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ScenarioError {
+    fn from(error: std::io::Error) -> Self {
+        ScenarioError::Io(error)
+    }
+}
+
+/// Static layout of a world, read from a plain text file with one entry per
+/// line:
+///
+/// ```text
+/// # comment
+/// topology walls | torus | open [despawn_radius]
+/// circle x y radius [blocks_light] [blocks_diffusion]
+/// polygon x1 y1 x2 y2 x3 y3 ... [blocks_light] [blocks_diffusion]
+/// polyline x1 y1 x2 y2 ... [blocks_light] [blocks_diffusion]
+/// mask path pixel_size [x y] [blocks_light] [blocks_diffusion]
+/// ```
+///
+/// A `mask` is a PBM image, plain (`P1`) or raw (`P4`), whose black pixels
+/// become one obstacle of `pixel_size` wide squares with the bottom left
+/// corner of the image at `x y`. Relative paths start from the scenario's
+/// directory. A `#` only starts a comment at the start of a line or
+/// after whitespace, so paths may contain one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scenario {
+    pub topology: Option<Topology>,
+    pub obstacles: Vec<Obstacle>,
+}
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let path = path.as_ref();
+        let directory = path.parent().unwrap_or(Path::new(""));
+        Self::parse_in(&std::fs::read_to_string(path)?, directory)
+    }
+
+    /// Parses a scenario with mask paths relative to the working directory.
+    pub fn parse(source: &str) -> Result<Self, ScenarioError> {
+        Self::parse_in(source, Path::new(""))
+    }
+
+    fn parse_in(source: &str, directory: &Path) -> Result<Self, ScenarioError> {
+        let mut scenario = Scenario::default();
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: String| ScenarioError::Parse {
+                line: index + 1,
+                message,
+            };
+            let line = strip_comment(line);
+            let mut words = line.split_whitespace();
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+
+            let mask_path = match keyword {
+                "mask" => Some(
+                    words
+                        .next()
+                        .ok_or_else(|| error("`mask` needs a path".into()))?,
+                ),
+                _ => None,
+            };
+            let mut numbers = Vec::new();
+            let mut obstacle = Obstacle::new(ObstacleShape::Polyline(Vec::new()));
+            let mut topology_name = None;
+            for word in words {
+                match word {
+                    "blocks_light" => obstacle.blocks_light = true,
+                    "blocks_diffusion" => obstacle.blocks_diffusion = true,
+                    "walls" | "torus" | "open" if keyword == "topology" => {
+                        topology_name = Some(word)
+                    }
+                    _ => numbers.push(
+                        word.parse::<f32>()
+                            .map_err(|_| error(format!("expected a number, found `{word}`")))?,
+                    ),
+                }
+            }
+
+            let points = |min: usize| {
+                if numbers.len() % 2 != 0 || numbers.len() < min * 2 {
+                    return Err(error(format!(
+                        "`{keyword}` needs at least {min} pairs of coordinates"
+                    )));
+                }
+                Ok(numbers
+                    .chunks(2)
+                    .map(|pair| vector![pair[0], pair[1]])
+                    .collect::<Vec<Vector2<f32>>>())
+            };
+
+            obstacle.shape = match keyword {
+                "topology" => {
+                    scenario.topology = Some(match (topology_name, numbers.as_slice()) {
+                        (Some("walls"), []) => Topology::Walls,
+                        (Some("torus"), []) => Topology::Torus,
+                        (Some("open"), []) => Topology::Open {
+                            despawn_radius: None,
+                        },
+                        (Some("open"), &[radius]) => Topology::Open {
+                            despawn_radius: Some(radius),
+                        },
+                        _ => {
+                            return Err(error(
+                                "expected `walls`, `torus` or `open [radius]`".into(),
+                            ))
+                        }
+                    });
+                    continue;
+                }
+                "circle" => match numbers.as_slice() {
+                    &[x, y, radius] => ObstacleShape::Circle {
+                        center: vector![x, y],
+                        radius,
+                    },
+                    _ => return Err(error("`circle` needs x, y and radius".into())),
+                },
+                "polygon" => ObstacleShape::Polygon(points(3)?),
+                "polyline" => ObstacleShape::Polyline(points(2)?),
+                "mask" => {
+                    let (pixel_size, origin) = match *numbers.as_slice() {
+                        [pixel_size] => (pixel_size, Vector2::zeros()),
+                        [pixel_size, x, y] => (pixel_size, vector![x, y]),
+                        _ => {
+                            return Err(error(
+                                "`mask` needs a pixel size and optionally x and y".into(),
+                            ))
+                        }
+                    };
+                    let path = directory.join(mask_path.unwrap());
+                    let (mask, width) = std::fs::read(&path)
+                        .map_err(|io_error| io_error.to_string())
+                        .and_then(|bytes| decode_pbm(&bytes))
+                        .map_err(|message| {
+                            error(format!("mask `{}`: {message}", path.display()))
+                        })?;
+                    scenario.obstacles.extend(
+                        Obstacle::from_mask(&mask, width, origin, pixel_size)
+                            .map(|mask_obstacle| Obstacle {
+                                blocks_light: obstacle.blocks_light,
+                                blocks_diffusion: obstacle.blocks_diffusion,
+                                ..mask_obstacle
+                            }),
+                    );
+                    continue;
+                }
+                _ => return Err(error(format!("unknown entry `{keyword}`"))),
+            };
+            if obstacle.shape.is_degenerate() {
+                return Err(error(format!("`{keyword}` is degenerate")));
+            }
+            scenario.obstacles.push(obstacle);
+        }
+
+        Ok(scenario)
+    }
+}
+
+/// `line` up to its comment, a `#` at the start of the line or after
+/// whitespace. Other `#`s, e.g. in mask paths, are kept.
+fn strip_comment(line: &str) -> &str {
+    let start = line.char_indices().find(|&(index, character)| {
+        character == '#' && line[..index].chars().next_back().is_none_or(char::is_whitespace)
+    });
+    match start {
+        Some((index, _)) => &line[..index],
+        None => line,
+    }
+}
+
+/// Next header token of a PBM image, skipping whitespace and comments.
+fn pbm_token<'a>(bytes: &'a [u8], position: &mut usize) -> Option<&'a [u8]> {
+    loop {
+        match *bytes.get(*position)? {
+            b'#' => {
+                while bytes.get(*position).is_some_and(|&byte| byte != b'\n') {
+                    *position += 1;
+                }
+            }
+            byte if byte.is_ascii_whitespace() => *position += 1,
+            _ => break,
+        }
+    }
+    let start = *position;
+    while bytes
+        .get(*position)
+        .is_some_and(|byte| !byte.is_ascii_whitespace() && *byte != b'#')
+    {
+        *position += 1;
+    }
+
+    Some(&bytes[start..*position])
+}
+
+/// Black pixels of a PBM image, row-major from the bottom row up so they
+/// match world coordinates, and the image width.
+fn decode_pbm(bytes: &[u8]) -> Result<(Vec<bool>, usize), String> {
+    let mut position = 0;
+    let magic = pbm_token(bytes, &mut position);
+    let mut size = || {
+        pbm_token(bytes, &mut position)
+            .and_then(|token| std::str::from_utf8(token).ok()?.parse::<usize>().ok())
+            .ok_or_else(|| "invalid PBM header".to_string())
+    };
+    let (width, height) = (size()?, size()?);
+    if width == 0 {
+        return Err("image is empty".into());
+    }
+
+    let pixels: Vec<bool> = match magic {
+        Some(b"P1") => {
+            let mut pixels = Vec::with_capacity(width * height);
+            let mut comment = false;
+            for &byte in &bytes[position..] {
+                match byte {
+                    b'\n' => comment = false,
+                    _ if comment => (),
+                    b'#' => comment = true,
+                    b'0' | b'1' => pixels.push(byte == b'1'),
+                    _ if byte.is_ascii_whitespace() => (),
+                    _ => return Err(format!("unexpected `{}` in pixels", byte as char)),
+                }
+            }
+            pixels
+        }
+        Some(b"P4") => {
+            // A single whitespace byte separates the header from the pixels
+            let row_bytes = width.div_ceil(8);
+            bytes
+                .get(position + 1..)
+                .unwrap_or_default()
+                .chunks(row_bytes)
+                .take(height)
+                .flat_map(|row| {
+                    (0..width).map(move |x| {
+                        row.get(x / 8)
+                            .is_some_and(|byte| byte & (0x80 >> (x % 8)) != 0)
+                    })
+                })
+                .collect()
+        }
+        _ => return Err("not a P1 or P4 PBM image".into()),
+    };
+    if pixels.len() < width * height {
+        return Err(format!(
+            "expected {} pixels, found {}",
+            width * height,
+            pixels.len()
+        ));
+    }
+    let pixels = pixels[..width * height]
+        .chunks(width)
+        .rev()
+        .flatten()
+        .copied()
+        .collect();
+
+    Ok((pixels, width))
+}
+
+impl World {
+    /// Sets the topology, if the scenario has one, and adds its obstacles.
+    pub fn apply_scenario(&mut self, scenario: &Scenario) {
+        if let Some(topology) = scenario.topology {
+            self.set_topology(topology);
+        }
+        scenario
+            .obstacles
+            .iter()
+            .for_each(|obstacle| self.add_obstacle(obstacle));
+    }
+}
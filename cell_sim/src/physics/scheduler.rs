@@ -0,0 +1,101 @@
+/// Shortest `physics_dt` and `chemistry_dt` the scheduler runs with, smaller
+/// or invalid ones are raised to it.
+pub const MIN_DT: f32 = 1e-4;
+
+/// Fixed-timestep clock driving `World::advance`. Physics runs every
+/// `physics_dt` of simulated time and chemistry every `chemistry_dt`,
+/// however often the caller advances the world.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    /// Simulated seconds per physics step, at least `MIN_DT`.
+    pub physics_dt: f32,
+    /// Simulated seconds between chemistry updates, at least `MIN_DT`.
+    pub chemistry_dt: f32,
+    /// Component iterations per chemistry update, each `STEP_SIZE` of cell
+    /// time.
+    pub chemistry_steps: usize,
+    /// Simulated seconds per real second.
+    pub speed: f32,
+    pub paused: bool,
+    /// Most physics steps a single `advance` may run. Time beyond that is
+    /// dropped so a slow frame cannot snowball.
+    pub max_steps: usize,
+    /// Most chemistry updates a single physics step may run, dropping time
+    /// the same way as `max_steps`.
+    pub max_chemistry_updates: usize,
+    time: f64,
+    ticks: u64,
+    pending: f32,
+    chemistry_pending: f32,
+}
+
+impl Scheduler {
+    /// Simulated seconds since the world was created.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Physics steps run since the world was created.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Raises time steps that are too small, zero or NaN to `MIN_DT`, which
+    /// would otherwise make the number of steps due unbounded.
+    fn clamp_dts(&mut self) {
+        self.physics_dt = self.physics_dt.max(MIN_DT);
+        self.chemistry_dt = self.chemistry_dt.max(MIN_DT);
+    }
+
+    /// Number of physics steps due after `dt` real seconds.
+    pub(super) fn steps_for(&mut self, dt: f32) -> usize {
+        if self.paused {
+            return 0;
+        }
+        self.clamp_dts();
+
+        self.pending += dt * self.speed;
+        let steps = ((self.pending / self.physics_dt) as usize).min(self.max_steps);
+        self.pending -= steps as f32 * self.physics_dt;
+        if steps == self.max_steps {
+            self.pending = self.pending.min(self.physics_dt);
+        }
+
+        steps
+    }
+
+    /// Moves the clock one physics step forward, returning how many chemistry
+    /// updates fell due during it.
+    pub(super) fn step(&mut self) -> usize {
+        self.clamp_dts();
+        self.time += self.physics_dt as f64;
+        self.ticks += 1;
+        self.chemistry_pending += self.physics_dt;
+        let due = ((self.chemistry_pending / self.chemistry_dt) as usize)
+            .min(self.max_chemistry_updates);
+        self.chemistry_pending -= due as f32 * self.chemistry_dt;
+        if due == self.max_chemistry_updates {
+            self.chemistry_pending = self.chemistry_pending.min(self.chemistry_dt);
+        }
+
+        due
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            physics_dt: 1. / 60.,
+            chemistry_dt: 1. / 60.,
+            chemistry_steps: 300,
+            speed: 1.,
+            paused: false,
+            max_steps: 8,
+            max_chemistry_updates: 8,
+            time: 0.,
+            ticks: 0,
+            pending: 0.,
+            chemistry_pending: 0.,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::cell::component::COMPONENT_COUNT;
+use crate::cell::genetics::genome::PROGRAM_COUNT;
+
+use super::cell_wrapper::CellWrapper;
+
+/// Summary of the population after the last `World::update`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Statistics {
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+    /// Physics steps the births and deaths were counted over.
+    pub steps: usize,
+    /// ATP held by all living cells.
+    pub atp: f32,
+    /// Glucose held by all living cells.
+    pub glucose: f32,
+    /// Living cells expressing each genome program.
+    pub cell_types: [usize; PROGRAM_COUNT],
+    /// Expression of each component summed over living cells, so a component
+    /// every cell has but few express stays low.
+    pub components: [f32; COMPONENT_COUNT],
+}
+
+impl Statistics {
+    pub fn collect(cells: &[Option<CellWrapper>], births: usize, deaths: usize, steps: usize) -> Self {
+        let mut statistics = Self {
+            births,
+            deaths,
+            steps,
+            ..Default::default()
+        };
+        cells.iter().flatten().for_each(|cell_wrapper| {
+            statistics.population += 1;
+            statistics.cell_types[cell_wrapper.inner.cell_type] += 1;
+            let chemicals = &cell_wrapper.inner.inner.chemicals;
+            statistics.atp += chemicals.atp;
+            statistics.glucose += chemicals.glucose;
+            cell_wrapper
+                .inner
+                .components
+                .iter()
+                .zip(statistics.components.iter_mut())
+                .for_each(|(component, total)| {
+                    if let Some(component) = component {
+                        *total += component.expression;
+                    }
+                });
+        });
+
+        statistics
+    }
+
+    /// Births per physics step.
+    pub fn birth_rate(&self) -> f32 {
+        self.births as f32 / self.steps.max(1) as f32
+    }
+
+    /// Deaths per physics step.
+    pub fn death_rate(&self) -> f32 {
+        self.deaths as f32 / self.steps.max(1) as f32
+    }
+}
@@ -0,0 +1,291 @@
+use nalgebra::{vector, Vector2};
+use rapier2d::dynamics::RigidBodyBuilder;
+use rapier2d::geometry::ColliderBuilder;
+
+use crate::cell::death::DeathCause;
+
+use super::world::{World, NOT_A_CELL};
+
+/// Thickness of the colliders used for `Topology::Walls`.
+const WALL_THICKNESS: f32 = 50.;
+/// Fraction of the overlap between cells touching across the edges of a
+/// torus removed per physics step.
+const WRAP_CONTACT_STIFFNESS: f32 = 0.2;
+
+/// What happens at the edges of the environment grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    /// Static colliders keep cells inside.
+    Walls,
+    /// Cells leaving one edge come back in on the opposite one, colonies
+    /// as a whole. Cells touch, bond and are found by queries across the
+    /// edges.
+    Torus,
+    /// Cells move freely, and are removed once further than `despawn_radius`
+    /// from the centre of the world.
+    Open { despawn_radius: Option<f32> },
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Topology::Open {
+            despawn_radius: None,
+        }
+    }
+}
+
+impl World {
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        if let Some(walls) = self.walls.take() {
+            self.rigid_body_set.remove(
+                walls,
+                &mut self.physics_props.island_manager,
+                &mut self.collider_set,
+                &mut self.physics_props.impulse_joint_set,
+                &mut self.physics_props.multibody_joint_set,
+                true,
+            );
+        }
+
+        if topology == Topology::Walls {
+            let (origin, size) = (self.environment.origin(), self.environment.size());
+            let walls = self
+                .rigid_body_set
+                .insert(RigidBodyBuilder::fixed().user_data(NOT_A_CELL).build());
+            let half = WALL_THICKNESS / 2.;
+            [
+                (
+                    vector![-half, size.y / 2.],
+                    vector![half, size.y / 2. + WALL_THICKNESS],
+                ),
+                (
+                    vector![size.x + half, size.y / 2.],
+                    vector![half, size.y / 2. + WALL_THICKNESS],
+                ),
+                (
+                    vector![size.x / 2., -half],
+                    vector![size.x / 2. + WALL_THICKNESS, half],
+                ),
+                (
+                    vector![size.x / 2., size.y + half],
+                    vector![size.x / 2. + WALL_THICKNESS, half],
+                ),
+            ]
+            .into_iter()
+            .for_each(|(center, half_extents)| {
+                let collider = ColliderBuilder::cuboid(half_extents.x, half_extents.y)
+                    .translation(origin + center)
+                    .user_data(NOT_A_CELL)
+                    .build();
+                self.collider_set
+                    .insert_with_parent(collider, walls, &mut self.rigid_body_set);
+            });
+            self.walls = Some(walls);
+        }
+
+        self.environment.wrap = topology == Topology::Torus;
+        self.topology = topology;
+    }
+
+    /// Shortest vector from `from` to `to`, going across the edges on a torus.
+    pub fn displacement(&self, from: Vector2<f32>, to: Vector2<f32>) -> Vector2<f32> {
+        let mut displacement = to - from;
+        if self.topology == Topology::Torus {
+            let size = self.environment.size();
+            displacement.x -= (displacement.x / size.x).round() * size.x;
+            displacement.y -= (displacement.y / size.y).round() * size.y;
+        }
+
+        displacement
+    }
+
+    /// Wraps cells around a torus or removes those that strayed too far in an
+    /// open world.
+    pub(super) fn apply_topology(&mut self) {
+        let (origin, size) = (self.environment.origin(), self.environment.size());
+        match self.topology {
+            Topology::Walls => {}
+            Topology::Torus => {
+                self.touch_across_edges();
+                self.wrap_colonies();
+            }
+            Topology::Open {
+                despawn_radius: Some(despawn_radius),
+            } => {
+                let center = origin + size / 2.;
+                let strays: Vec<usize> = self
+                    .cells
+                    .iter()
+                    .flatten()
+                    .filter(|cell_wrapper| {
+                        let rigid_body = &self.rigid_body_set[cell_wrapper.rigid_body_handle];
+                        (rigid_body.translation() - center).norm() > despawn_radius
+                    })
+                    .map(|cell_wrapper| cell_wrapper.index)
+                    .collect();
+                strays
+                    .into_iter()
+                    .for_each(|cell_idx| self.kill_cell(cell_idx, DeathCause::OutOfBounds));
+            }
+            Topology::Open {
+                despawn_radius: None,
+            } => {}
+        }
+    }
+
+    /// Offset that brings `position` back inside a torus.
+    fn wrap_offset(&self, position: Vector2<f32>) -> Vector2<f32> {
+        let (origin, size) = (self.environment.origin(), self.environment.size());
+        let local = position - origin;
+        vector![local.x.rem_euclid(size.x), local.y.rem_euclid(size.y)] - local
+    }
+
+    fn shift_cells(&mut self, cells: &[usize], offset: Vector2<f32>) {
+        cells.iter().for_each(|&cell_idx| {
+            if let Some(cell_wrapper) = &self.cells[cell_idx] {
+                let rigid_body = &mut self.rigid_body_set[cell_wrapper.rigid_body_handle];
+                let position = rigid_body.translation() + offset;
+                rigid_body.set_translation(position, true);
+            }
+        });
+    }
+
+    /// Brings every colony whose centre left a torus back in as a whole, so
+    /// bonds never stretch across the world. Its members may stay outside
+    /// the edges.
+    fn wrap_colonies(&mut self) {
+        let mut in_colony = vec![false; self.cells.len()];
+        self.colonies().into_iter().for_each(|colony| {
+            colony.iter().for_each(|&member| in_colony[member] = true);
+            let sum = colony
+                .iter()
+                .filter_map(|&member| self.cell_position(member))
+                .fold(Vector2::zeros(), |sum, position| sum + position);
+            let offset = self.wrap_offset(sum / colony.len() as f32);
+            if offset != Vector2::zeros() {
+                self.shift_cells(&colony, offset);
+            }
+        });
+
+        (0..self.cells.len())
+            .filter(|&cell_idx| !in_colony[cell_idx])
+            .for_each(|cell_idx| {
+                if let Some(position) = self.cell_position(cell_idx) {
+                    let offset = self.wrap_offset(position);
+                    if offset != Vector2::zeros() {
+                        self.shift_cells(&[cell_idx], offset);
+                    }
+                }
+            });
+    }
+
+    /// Shortest vector between two cells and how far they overlap.
+    fn contact(&self, cell_a: usize, cell_b: usize) -> Option<(Vector2<f32>, f32)> {
+        let displacement =
+            self.displacement(self.cell_position(cell_a)?, self.cell_position(cell_b)?);
+        let radii =
+            self.cells[cell_a].as_ref()?.inner.size() + self.cells[cell_b].as_ref()?.inner.size();
+
+        Some((displacement, radii - displacement.norm()))
+    }
+
+    /// Rapier does not know the world wraps, so cells touching across
+    /// the edges of a torus are pushed apart here, or bonded after moving one
+    /// colony next to the other.
+    fn touch_across_edges(&mut self) {
+        // The queries still see where cells were before this physics step
+        self.refresh_queries();
+        let pairs: Vec<(usize, usize)> = self
+            .cells
+            .iter()
+            .flatten()
+            .flat_map(|cell_wrapper| {
+                let position = self.cell_position(cell_wrapper.index).unwrap();
+                let radius = cell_wrapper.inner.size();
+                self.images(position, radius)
+                    .filter(|image| *image != position)
+                    .flat_map(|image| self.cells_overlapping(image, radius))
+                    .filter(|&other| cell_wrapper.index < other)
+                    .map(|other| (cell_wrapper.index, other))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if pairs.is_empty() {
+            return;
+        }
+
+        // Colony label and members of every cell, merged as bonds form
+        let mut colony_of: Vec<usize> = (0..self.cells.len()).collect();
+        let mut members: Vec<Vec<usize>> =
+            (0..self.cells.len()).map(|cell_idx| vec![cell_idx]).collect();
+        self.colonies().into_iter().for_each(|colony| {
+            let label = colony[0];
+            colony.iter().for_each(|&member| colony_of[member] = label);
+            members[label] = colony;
+        });
+
+        pairs.into_iter().for_each(|(cell_a, cell_b)| {
+            let Some((displacement, overlap)) = self.contact(cell_a, cell_b) else {
+                return;
+            };
+            if overlap < 0. {
+                return;
+            }
+            let (colony_a, colony_b) = (colony_of[cell_a], colony_of[cell_b]);
+            if colony_a != colony_b && self.bondable(cell_a, cell_b) {
+                let (position_a, position_b) = (
+                    self.cell_position(cell_a).unwrap(),
+                    self.cell_position(cell_b).unwrap(),
+                );
+                self.shift_cells(&members[colony_b], position_a + displacement - position_b);
+                self.bond(cell_a, cell_b);
+                let moved = std::mem::take(&mut members[colony_b]);
+                moved.iter().for_each(|&member| colony_of[member] = colony_a);
+                members[colony_a].extend(moved);
+            } else {
+                self.push_apart(cell_a, cell_b, displacement, overlap);
+            }
+        });
+    }
+
+    /// Impulses a contact would apply to separate two overlapping cells along
+    /// `displacement`, the shortest vector from `cell_a` to `cell_b`.
+    fn push_apart(
+        &mut self,
+        cell_a: usize,
+        cell_b: usize,
+        displacement: Vector2<f32>,
+        overlap: f32,
+    ) {
+        let (Some(wrapper_a), Some(wrapper_b)) = (&self.cells[cell_a], &self.cells[cell_b]) else {
+            return;
+        };
+        let Some(normal) = displacement.try_normalize(f32::EPSILON) else {
+            return;
+        };
+        let mass_a = self.collider_set[wrapper_a.collider_handle]
+            .mass_properties()
+            .mass();
+        let mass_b = self.collider_set[wrapper_b.collider_handle]
+            .mass_properties()
+            .mass();
+        if mass_a <= 0. || mass_b <= 0. {
+            return;
+        }
+        let (body_a, body_b) = (wrapper_a.rigid_body_handle, wrapper_b.rigid_body_handle);
+
+        let dt = self.physics_props.integration_parameters.dt;
+        let approach = (self.rigid_body_set[body_b].linvel()
+            - self.rigid_body_set[body_a].linvel())
+        .dot(&normal)
+        .min(0.);
+        let reduced_mass = mass_a * mass_b / (mass_a + mass_b);
+        let impulse = normal * reduced_mass * (overlap * WRAP_CONTACT_STIFFNESS / dt - approach);
+        self.rigid_body_set[body_a].apply_impulse(-impulse, true);
+        self.rigid_body_set[body_b].apply_impulse(impulse, true);
+    }
+}
@@ -1,28 +1,43 @@
 use nalgebra::Vector2;
 use rapier2d::dynamics::RigidBodySet;
 use rapier2d::geometry::ColliderSet;
-use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+use crate::cell::chemicals::Chemicals;
+use crate::cell::config::CellConfig;
+use crate::cell::inner::MIN_WATER;
 
 use super::cell_wrapper::CellWrapper;
 use super::physics_props::PhysicsPropsStruct;
 use super::world::CellChanges;
 
-pub fn update_cells(cells: &mut [Option<CellWrapper>]) -> Vec<Option<CellChanges>> {
+/// Runs `steps` component iterations on every cell, covering `dt` simulated
+/// seconds the cells age by.
+pub fn update_cells(
+    cells: &mut [Option<CellWrapper>],
+    config: &CellConfig,
+    steps: usize,
+    dt: f32,
+) -> Vec<Option<CellChanges>> {
     let update = |cell: &mut CellWrapper| {
-        for _ in 0..300 {
-            if cell.inner.dead { return None }
-            cell.inner.run_components();
+        for _ in 0..steps {
+            if cell.inner.is_dead() { return None }
+            cell.inner.run_components(config);
         }
+        cell.inner.grow_older(dt, config);
         let impulse = match cell.inner.velocity_changed {
             true => {
                 let impulse = cell.inner.impulse;
                 cell.inner.impulse = Vector2::new(0., 0.);
+                cell.inner.velocity_changed = false;
                 Some(impulse)
             }
             false => None,
         };
         let size = match cell.inner.size_changed {
             true => {
+                cell.inner.size_changed = false;
                 Some(cell.inner.size())
             }
             false => None,
@@ -44,6 +59,84 @@ pub fn update_cells(cells: &mut [Option<CellWrapper>]) -> Vec<Option<CellChanges
     collection
 }
 
+/// Moves ATP, glucose and nucleotides across every bond down the
+/// concentration gradient between the two cells. Flows are computed from a
+/// snapshot in parallel, then scaled so that no cell gives away more than it
+/// holds over all of its bonds, and applied in order.
+pub fn share_resources(
+    cells: &mut [Option<CellWrapper>],
+    bonds: &[(usize, usize)],
+    config: &CellConfig,
+) {
+    let cells_ref = &*cells;
+    let flow = |&(cell_a, cell_b): &(usize, usize)| {
+        let a = &cells_ref[cell_a].as_ref()?.inner.inner;
+        let b = &cells_ref[cell_b].as_ref()?.inner.inner;
+        let (water_a, water_b) = (a.water.max(MIN_WATER), b.water.max(MIN_WATER));
+        // Amount that would equalise the concentrations, scaled by the rate
+        let share = |amount_a: f32, amount_b: f32| {
+            (amount_a / water_a - amount_b / water_b) * water_a * water_b / (water_a + water_b)
+                * config.sharing_rate
+        };
+
+        Some((cell_a, cell_b, [
+            share(a.chemicals.atp, b.chemicals.atp),
+            share(a.chemicals.glucose, b.chemicals.glucose),
+            share(a.nucleotides, b.nucleotides),
+        ]))
+    };
+
+    #[cfg(feature = "parallel")]
+    let flows: Vec<(usize, usize, [f32; 3])> = bonds.par_iter().filter_map(flow).collect();
+    #[cfg(not(feature = "parallel"))]
+    let flows: Vec<(usize, usize, [f32; 3])> = bonds.iter().filter_map(flow).collect();
+
+    // What each cell would give away in total, per resource
+    let mut outflow = vec![[0.; 3]; cells.len()];
+    flows.iter().for_each(|&(cell_a, cell_b, amounts)| {
+        amounts.iter().enumerate().for_each(|(resource, &amount)| {
+            let giver = if amount > 0. { cell_a } else { cell_b };
+            outflow[giver][resource] += amount.abs();
+        });
+    });
+    let scale: Vec<[f32; 3]> = cells
+        .iter()
+        .zip(outflow.iter())
+        .map(|(cell_wrapper, outflow)| {
+            let Some(cell_wrapper) = cell_wrapper else {
+                return [0.; 3];
+            };
+            let inner = &cell_wrapper.inner.inner;
+            let held = [inner.chemicals.atp, inner.chemicals.glucose, inner.nucleotides];
+            let mut scale = [1.; 3];
+            (0..3).for_each(|resource| {
+                if outflow[resource] > held[resource] {
+                    scale[resource] = held[resource].max(0.) / outflow[resource];
+                }
+            });
+            scale
+        })
+        .collect();
+
+    flows.into_iter().for_each(|(cell_a, cell_b, mut amounts)| {
+        amounts.iter_mut().enumerate().for_each(|(resource, amount)| {
+            let giver = if *amount > 0. { cell_a } else { cell_b };
+            *amount *= scale[giver][resource];
+        });
+        let chemicals = Chemicals {
+            atp: amounts[0],
+            glucose: amounts[1],
+            ..Default::default()
+        };
+        if let Some(cell_wrapper) = cells[cell_a].as_mut() {
+            cell_wrapper.inner.exchange(chemicals * -1., -amounts[2]);
+        }
+        if let Some(cell_wrapper) = cells[cell_b].as_mut() {
+            cell_wrapper.inner.exchange(chemicals, amounts[2]);
+        }
+    });
+}
+
 pub fn update_physics(
     physics_props: &mut PhysicsPropsStruct,
     rigid_body_set: &mut RigidBodySet,
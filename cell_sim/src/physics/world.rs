@@ -1,14 +1,23 @@
 use std::time::Duration;
 
 use crate::cell::component::ComponentProps;
+use crate::cell::config::CellConfig;
+use crate::cell::death::DeathCause;
 use crate::cell::Cell;
-use crate::physics::updates::{update_physics, update_cells};
-use nalgebra::Vector2;
+use crate::physics::updates::{share_resources, update_physics, update_cells};
+use nalgebra::{vector, Vector2};
 use rapier2d::dynamics::{RigidBody, RigidBodyBuilder, RigidBodyHandle, RigidBodySet};
 use rapier2d::geometry::{Collider, ColliderBuilder, ColliderHandle, ColliderSet, SharedShape};
 
 use super::cell_wrapper::CellWrapper;
+use super::environment::Environment;
 use super::physics_props::PhysicsPropsStruct;
+use super::scheduler::Scheduler;
+use super::statistics::Statistics;
+use super::topology::Topology;
+
+/// `user_data` of colliders and bodies that do not belong to a cell.
+pub(super) const NOT_A_CELL: u128 = u128::MAX;
 
 #[derive(Default)]
 pub struct World {
@@ -16,8 +25,21 @@ pub struct World {
     pub rigid_body_set: RigidBodySet,
     pub collider_set: ColliderSet,
     pub physics_props: PhysicsPropsStruct,
+    pub cell_config: CellConfig,
+    pub environment: Environment,
+    /// Cells that died during the last `update`.
+    pub death_events: Vec<DeathEvent>,
+    /// Cells that were born during the last `update`.
+    pub birth_events: Vec<BirthEvent>,
+    pub statistics: Statistics,
+    pub scheduler: Scheduler,
 
     free_indexes: Vec<usize>,
+    next_id: u64,
+    pub(super) topology: Topology,
+    pub(super) walls: Option<RigidBodyHandle>,
+    /// How far cells reach past the edges of a torus, see `World::images`.
+    pub(super) overhang: f32,
 
     #[cfg(debug_assertions)]
     pub cell_time: Duration,
@@ -34,6 +56,19 @@ pub struct CellChanges {
     pub size: Option<f32>,
 }
 
+pub struct DeathEvent {
+    pub index: usize,
+    pub cause: DeathCause,
+    pub position: Vector2<f32>,
+    /// State of the cell when it died.
+    pub cell: Cell,
+}
+
+pub struct BirthEvent {
+    pub index: usize,
+    pub parent: usize,
+}
+
 impl World {
     fn inject_cell(
         &mut self,
@@ -41,11 +76,16 @@ impl World {
         collider_handle: ColliderHandle,
         rigid_body_handle: RigidBodyHandle,
     ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
         let mut cell_wrapper = CellWrapper {
             inner: cell,
             collider_handle,
             rigid_body_handle,
             index: 0,
+            id,
+            parent: None,
+            founder: id,
         };
         match self.free_indexes.pop() {
             Some(index) => {
@@ -76,7 +116,11 @@ impl World {
             &mut self.rigid_body_set,
         );
 
-        self.inject_cell(cell, collider_handle, rigid_body_handle)
+        let index = self.inject_cell(cell, collider_handle, rigid_body_handle);
+        self.rigid_body_set[rigid_body_handle].user_data = index as u128;
+        self.collider_set[collider_handle].user_data = index as u128;
+
+        index
     }
 
     pub fn add_cell(&mut self, cell: Cell, position: Vector2<f32>) -> usize {
@@ -87,8 +131,109 @@ impl World {
     }
 
     pub fn remove_cell(&mut self, cell_idx: usize) {
-        self.cells[cell_idx] = None;
-        self.free_indexes.push(cell_idx)
+        if let Some(cell_wrapper) = self.cells[cell_idx].take() {
+            self.rigid_body_set.remove(
+                cell_wrapper.rigid_body_handle,
+                &mut self.physics_props.island_manager,
+                &mut self.collider_set,
+                &mut self.physics_props.impulse_joint_set,
+                &mut self.physics_props.multibody_joint_set,
+                true,
+            );
+            self.free_indexes.push(cell_idx)
+        }
+    }
+
+    pub fn kill_cell(&mut self, cell_idx: usize, cause: DeathCause) {
+        if let Some(cell_wrapper) = self.cells.get_mut(cell_idx).and_then(Option::as_mut) {
+            cell_wrapper.inner.kill(cause);
+        }
+    }
+
+    pub fn cell_position(&self, cell_idx: usize) -> Option<Vector2<f32>> {
+        let cell_wrapper = self.cells.get(cell_idx)?.as_ref()?;
+        let rigid_body = self.rigid_body_set.get(cell_wrapper.rigid_body_handle)?;
+
+        Some(rigid_body.position().translation.vector)
+    }
+
+    /// Copies the environment around each cell into `Cell::surroundings`.
+    fn sample_surroundings(&mut self) {
+        let (rigid_body_set, environment) = (&self.rigid_body_set, &self.environment);
+        self.cells.iter_mut().flatten().for_each(|cell_wrapper| {
+            let rigid_body = &rigid_body_set[cell_wrapper.rigid_body_handle];
+            let position = rigid_body.position().translation.vector;
+            cell_wrapper.inner.surroundings = *environment.get(position);
+            cell_wrapper.inner.light = environment.light(position);
+        });
+    }
+
+    /// Moves what cells exported through their membranes into the environment.
+    fn collect_exports(&mut self) {
+        let (rigid_body_set, environment) = (&self.rigid_body_set, &mut self.environment);
+        self.cells.iter_mut().flatten().for_each(|cell_wrapper| {
+            let exported = std::mem::take(&mut cell_wrapper.inner.exported);
+            let rigid_body = &rigid_body_set[cell_wrapper.rigid_body_handle];
+            environment.deposit(rigid_body.position().translation.vector, exported);
+        });
+    }
+
+    /// Removes dead cells, recording a `DeathEvent` for each and releasing
+    /// their `Cell::remains` into the environment where they died.
+    pub(super) fn remove_dead_cells(&mut self) {
+        (0..self.cells.len()).for_each(|idx| {
+            let death = match &self.cells[idx] {
+                Some(cell_wrapper) => cell_wrapper.inner.death.map(|cause| (cause, cell_wrapper.inner)),
+                None => None,
+            };
+            if let Some((cause, cell)) = death {
+                let position = self.cell_position(idx).unwrap();
+                if !matches!(cause, DeathCause::OutOfBounds | DeathCause::Removed) {
+                    self.environment.deposit(position, cell.remains());
+                }
+                self.death_events.push(DeathEvent {
+                    index: idx,
+                    cause,
+                    position,
+                    cell,
+                });
+                self.remove_cell(idx);
+            }
+        });
+    }
+
+    /// Splits every cell ready to divide, placing the daughter next to its
+    /// parent. The daughter's program is chosen from the parent's position in
+    /// its colony and the daughter's own internal state.
+    fn divide_cells(&mut self) {
+        let config = self.cell_config;
+        let dividing: Vec<usize> = self
+            .cells
+            .iter()
+            .flatten()
+            .filter(|cell_wrapper| cell_wrapper.inner.should_divide(&config))
+            .map(|cell_wrapper| cell_wrapper.index)
+            .collect();
+
+        dividing.into_iter().for_each(|parent| {
+            let bonds = self.bonded_cells(parent).len();
+            let position = self.cell_position(parent).unwrap();
+            let cell_wrapper = self.cells[parent].as_mut().unwrap();
+            let mut daughter = cell_wrapper.inner.divide(&config);
+            daughter.cell_type = daughter.genome.select_program(bonds, &daughter.inner);
+
+            let radius = cell_wrapper.inner.size();
+            self.collider_set[cell_wrapper.collider_handle].set_shape(SharedShape::ball(radius));
+
+            let angle = rand::random::<f32>() * std::f32::consts::TAU;
+            let offset = vector![angle.cos(), angle.sin()] * (radius + daughter.size());
+            let (parent_id, founder) = (cell_wrapper.id, cell_wrapper.founder);
+            let index = self.add_cell(daughter, position + offset);
+            let daughter_wrapper = self.cells[index].as_mut().unwrap();
+            daughter_wrapper.parent = Some(parent_id);
+            daughter_wrapper.founder = founder;
+            self.birth_events.push(BirthEvent { index, parent });
+        });
     }
 
     pub fn inject_component(
@@ -109,22 +254,85 @@ impl World {
         }
     }
 
+    /// Simulated seconds since the world was created.
+    pub fn time(&self) -> f64 {
+        self.scheduler.time()
+    }
+
+    /// Runs a single physics step, ignoring `Scheduler::paused`, so a paused
+    /// world can be stepped through.
     pub fn update(&mut self) {
-        let mut cell_changes: Vec<Option<CellChanges>> = Vec::with_capacity(self.cells.len());
+        self.run_steps(1);
+    }
+
+    /// Runs as many physics and chemistry steps as fit in `dt` real seconds
+    /// at the scheduler's speed.
+    pub fn advance(&mut self, dt: f32) {
+        let steps = self.scheduler.steps_for(dt);
+        self.run_steps(steps);
+    }
+
+    /// Runs steps back to back for roughly `budget` of real time, ignoring
+    /// the scheduler's speed and pause, and returns how many ran.
+    pub fn run_for(&mut self, budget: Duration) -> usize {
+        self.death_events.clear();
+        self.birth_events.clear();
+        let start_time = std::time::Instant::now();
+        let mut steps = 0;
+        while steps == 0 || start_time.elapsed() < budget {
+            self.step();
+            steps += 1;
+        }
+        self.collect_statistics(steps);
+
+        steps
+    }
+
+    /// Runs `steps` physics steps. Events accumulate over all of them.
+    pub fn run_steps(&mut self, steps: usize) {
+        self.death_events.clear();
+        self.birth_events.clear();
+        (0..steps).for_each(|_| self.step());
+        self.collect_statistics(steps);
+    }
+
+    fn collect_statistics(&mut self, steps: usize) {
+        self.statistics =
+            Statistics::collect(&self.cells, self.birth_events.len(), self.death_events.len(), steps);
+    }
+
+    fn step(&mut self) {
+        let chemistry_updates = self.scheduler.step();
+        let chemistry_steps = chemistry_updates * self.scheduler.chemistry_steps;
+        let chemistry_time = chemistry_updates as f32 * self.scheduler.chemistry_dt;
+        self.physics_props.integration_parameters.dt = self.scheduler.physics_dt;
+        if chemistry_updates > 0 {
+            self.sample_surroundings();
+        }
+
         #[cfg(feature = "parallel")]
-        {
+        let cell_changes = {
+            let mut cell_changes: Vec<Option<CellChanges>> = Vec::with_capacity(self.cells.len());
+            #[cfg(debug_assertions)]
             let mut update_cells_time = Duration::default();
+            #[cfg(debug_assertions)]
             let mut update_physics_time = Duration::default();
             rayon::join(
                 || {
+                    #[cfg(debug_assertions)]
                     let start_time = std::time::Instant::now();
-                    cell_changes = update_cells(&mut self.cells);
-                    update_cells_time = start_time.elapsed();
+                    cell_changes = update_cells(&mut self.cells, &self.cell_config, chemistry_steps, chemistry_time);
+                    #[cfg(debug_assertions)] {
+                        update_cells_time = start_time.elapsed();
+                    }
                 },
                 || {
+                    #[cfg(debug_assertions)]
                     let start_time = std::time::Instant::now();
                     update_physics(&mut self.physics_props, &mut self.rigid_body_set, &mut self.collider_set);
-                    update_physics_time = start_time.elapsed();
+                    #[cfg(debug_assertions)] {
+                        update_physics_time = start_time.elapsed();
+                    }
                 }
             );
 
@@ -132,31 +340,55 @@ impl World {
                 self.cell_time += update_cells_time;
                 self.physics_time += update_physics_time;
             }
-        }
 
-        #[cfg(not(feature = "parallel"))] {
-            let cell_changes = update_cells(self.cells.as_mut_slice());
+            cell_changes
+        };
+
+        #[cfg(not(feature = "parallel"))]
+        let cell_changes = {
+            #[cfg(debug_assertions)]
+            let start_time = std::time::Instant::now();
+            let cell_changes = update_cells(self.cells.as_mut_slice(), &self.cell_config, chemistry_steps, chemistry_time);
+            #[cfg(debug_assertions)]
+            let update_cells_time = start_time.elapsed();
             update_physics(&mut self.physics_props, &mut self.rigid_body_set, &mut self.collider_set);
-        }
 
+            #[cfg(debug_assertions)] {
+                self.cell_time += update_cells_time;
+                self.physics_time += start_time.elapsed() - update_cells_time;
+            }
+
+            cell_changes
+        };
+
+        #[cfg(debug_assertions)]
         let start_time = std::time::Instant::now();
-        cell_changes.iter().enumerate().for_each(|( idx, change_option )| {
-            match change_option {
-                Some(change) => {
-                    if let Some(impulse) = change.impulse {
-                        let rigid_body = self.rigid_body_set.get_mut(change.rigid_body_handle).unwrap();
-                        rigid_body.apply_impulse(impulse * 100., true);
-                    }
-                    if let Some(size) = change.size {
-                        let collider = self.collider_set.get_mut(change.collider_handle).unwrap();
-                        collider.set_shape(SharedShape::ball(size));
-                    }
-                }
-                None => {
-                    self.remove_cell(idx);
-                }
+        cell_changes.iter().flatten().for_each(|change| {
+            if let Some(impulse) = change.impulse {
+                let rigid_body = self.rigid_body_set.get_mut(change.rigid_body_handle).unwrap();
+                rigid_body.apply_impulse(impulse * 100., true);
+            }
+            if let Some(size) = change.size {
+                let collider = self.collider_set.get_mut(change.collider_handle).unwrap();
+                collider.set_shape(SharedShape::ball(size));
             }
         });
+        self.apply_drag();
+        if chemistry_updates > 0 {
+            let bonds = self.bonds();
+            share_resources(&mut self.cells, &bonds, &self.cell_config);
+            self.collect_exports();
+        }
+        self.apply_topology();
+        self.remove_dead_cells();
+        self.divide_cells();
+        self.break_bonds();
+        self.form_bonds();
+        (0..chemistry_updates).for_each(|_| {
+            self.environment.advect(self.scheduler.chemistry_dt);
+            self.environment.diffuse();
+        });
+        self.refresh_queries();
         #[cfg(debug_assertions)] {
             self.replication_time += start_time.elapsed();
         }
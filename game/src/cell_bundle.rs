@@ -1,11 +1,23 @@
 use bevy::prelude::*;
-use bevy::sprite::MaterialMesh2dBundle;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
 
 #[derive(Component, Clone, Copy, Debug)]
 pub struct CellId {
     pub cell_id: usize,
 }
 
+/// Unit circle shared by every cell, scaled to the cell's radius through its
+/// `Transform`.
+#[derive(Resource)]
+pub struct CellMesh(pub Mesh2dHandle);
+
+impl FromWorld for CellMesh {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        Self(meshes.add(shape::Circle::new(1.).into()).into())
+    }
+}
+
 #[derive(Bundle, Clone)]
 pub struct CellBundle {
     pub material_mesh_bundle: MaterialMesh2dBundle<ColorMaterial>,
@@ -14,17 +26,18 @@ pub struct CellBundle {
 
 impl CellBundle {
     pub fn new(
-        meshes: &mut Assets<Mesh>,
-        materials: &mut Assets<ColorMaterial>,
+        mesh: Mesh2dHandle,
+        material: Handle<ColorMaterial>,
         pos: Vec2,
         size: f32,
         cell_id: usize,
     ) -> Self {
         Self {
             material_mesh_bundle: MaterialMesh2dBundle {
-                mesh: meshes.add(shape::Circle::new(size).into()).into(),
-                material: materials.add(ColorMaterial::from(Color::PURPLE)),
-                transform: Transform::from_xyz(pos.x, pos.y, 0.),
+                mesh,
+                material,
+                transform: Transform::from_xyz(pos.x, pos.y, 0.)
+                    .with_scale(Vec3::new(size, size, 1.)),
                 ..default()
             },
             cell_id: CellId { cell_id },
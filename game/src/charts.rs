@@ -0,0 +1,252 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use cell_sim::cell::component::COMPONENT_NAMES;
+use cell_sim::physics::Statistics;
+
+use crate::coloring::Palette;
+use crate::world_wrapper::WorldWrapper;
+
+/// Samples kept per chart, one pixel column each.
+const HISTORY: usize = 240;
+const CHART_HEIGHT: usize = 60;
+const BACKGROUND: [u8; 4] = [30, 30, 30, 255];
+
+/// One plot in the charts panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chart {
+    Population,
+    BirthsDeaths,
+    Chemicals,
+    Components,
+}
+
+impl Chart {
+    const ALL: [Chart; 4] = [
+        Chart::Population,
+        Chart::BirthsDeaths,
+        Chart::Chemicals,
+        Chart::Components,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Chart::Population => "Population",
+            Chart::BirthsDeaths => "Per tick",
+            Chart::Chemicals => "Total in cells",
+            Chart::Components => "Total expression",
+        }
+    }
+
+    /// Names and colors of the plotted lines, in the order of `values`.
+    fn lines(&self) -> Vec<(&'static str, Color)> {
+        match self {
+            Chart::Population => vec![("cells", Color::WHITE)],
+            Chart::BirthsDeaths => vec![("births", Color::GREEN), ("deaths", Color::RED)],
+            Chart::Chemicals => vec![("ATP", Color::YELLOW), ("glucose", Color::CYAN)],
+            Chart::Components => COMPONENT_NAMES
+                .iter()
+                .enumerate()
+                .map(|(index, name)| (*name, Palette::category_color(index)))
+                .collect(),
+        }
+    }
+
+    fn values(&self, statistics: &Statistics) -> Vec<f32> {
+        match self {
+            Chart::Population => vec![statistics.population as f32],
+            Chart::BirthsDeaths => vec![statistics.birth_rate(), statistics.death_rate()],
+            Chart::Chemicals => vec![statistics.atp, statistics.glucose],
+            Chart::Components => statistics.components.to_vec(),
+        }
+    }
+}
+
+/// Rolling history of `World::statistics` and the images it is plotted to.
+#[derive(Resource)]
+pub struct Charts {
+    pub visible: bool,
+    history: VecDeque<Statistics>,
+    last_tick: u64,
+    images: Vec<Handle<Image>>,
+}
+
+#[derive(Component)]
+pub struct ChartsPanel;
+
+/// Legend above the chart with this index in `Chart::ALL`.
+#[derive(Component)]
+pub struct ChartLegend(usize);
+
+pub fn spawn_charts(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let handles: Vec<Handle<Image>> = Chart::ALL
+        .iter()
+        .map(|_| {
+            let mut image = Image::new_fill(
+                Extent3d {
+                    width: HISTORY as u32,
+                    height: CHART_HEIGHT as u32,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &BACKGROUND,
+                TextureFormat::Rgba8UnormSrgb,
+            );
+            image.sampler = ImageSampler::nearest();
+            images.add(image)
+        })
+        .collect();
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.),
+                    left: Val::Px(10.),
+                    padding: UiRect::all(Val::Px(8.)),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                ..default()
+            },
+            ChartsPanel,
+        ))
+        .with_children(|panel| {
+            handles.iter().enumerate().for_each(|(index, handle)| {
+                panel.spawn((TextBundle::default(), ChartLegend(index)));
+                panel.spawn(ImageBundle {
+                    image: UiImage::new(handle.clone()),
+                    style: Style {
+                        width: Val::Px(HISTORY as f32),
+                        height: Val::Px(CHART_HEIGHT as f32),
+                        ..default()
+                    },
+                    ..default()
+                });
+            });
+        });
+    commands.insert_resource(Charts {
+        visible: false,
+        history: VecDeque::with_capacity(HISTORY),
+        last_tick: 0,
+        images: handles,
+    });
+}
+
+pub fn toggle_charts(keyboard_input: Res<Input<KeyCode>>, mut charts: ResMut<Charts>) {
+    if keyboard_input.just_pressed(KeyCode::G) {
+        charts.visible = !charts.visible;
+    }
+}
+
+/// Samples the statistics whenever the world has moved on since the last
+/// sample, also while the panel is hidden.
+pub fn record_statistics(world_wrapper: Res<WorldWrapper>, mut charts: ResMut<Charts>) {
+    let world = world_wrapper.world();
+    let tick = world.scheduler.ticks();
+    if tick == charts.last_tick {
+        return;
+    }
+    charts.last_tick = tick;
+    if charts.history.len() == HISTORY {
+        charts.history.pop_front();
+    }
+    charts.history.push_back(world.statistics);
+}
+
+pub fn update_charts(
+    charts: Res<Charts>,
+    mut images: ResMut<Assets<Image>>,
+    mut panel_query: Query<&mut Style, With<ChartsPanel>>,
+    mut legend_query: Query<(&ChartLegend, &mut Text)>,
+) {
+    let mut style = panel_query.single_mut();
+    if !charts.visible {
+        style.display = Display::None;
+        return;
+    }
+    style.display = Display::Flex;
+
+    let latest = charts.history.back().copied().unwrap_or_default();
+    legend_query.iter_mut().for_each(|(legend, mut text)| {
+        let chart = Chart::ALL[legend.0];
+        let section = |value: String, color: Color| {
+            TextSection::new(
+                value,
+                TextStyle {
+                    font_size: 12.,
+                    color,
+                    ..default()
+                },
+            )
+        };
+        text.sections = vec![section(format!("{}:", chart.title()), Color::WHITE)];
+        text.sections.extend(
+            chart
+                .lines()
+                .into_iter()
+                .zip(chart.values(&latest))
+                .map(|((name, color), value)| section(format!(" {} {:.2}", name, value), color)),
+        );
+    });
+
+    Chart::ALL
+        .iter()
+        .zip(charts.images.iter())
+        .for_each(|(chart, handle)| {
+            if let Some(image) = images.get_mut(handle) {
+                plot(*chart, &charts.history, image);
+            }
+        });
+}
+
+/// Redraws `image` with one line per value of `chart`, scaled to the largest
+/// value in the history.
+fn plot(chart: Chart, history: &VecDeque<Statistics>, image: &mut Image) {
+    image
+        .data
+        .chunks_exact_mut(4)
+        .for_each(|pixel| pixel.copy_from_slice(&BACKGROUND));
+
+    let samples: Vec<Vec<f32>> = history
+        .iter()
+        .map(|statistics| chart.values(statistics))
+        .collect();
+    let max = samples
+        .iter()
+        .flatten()
+        .copied()
+        .fold(0., f32::max)
+        .max(f32::EPSILON);
+    let row = |value: f32| ((value / max) * (CHART_HEIGHT - 1) as f32).round() as usize;
+
+    chart
+        .lines()
+        .iter()
+        .enumerate()
+        .for_each(|(line, (_, color))| {
+            let [r, g, b, _] = color.as_rgba_u8();
+            let mut previous = None;
+            samples.iter().enumerate().for_each(|(x, values)| {
+                let y = row(values[line]);
+                // Fill the column up to the previous sample so steep changes
+                // stay connected
+                let (low, high) = match previous {
+                    Some(previous) => (y.min(previous), y.max(previous)),
+                    None => (y, y),
+                };
+                (low..=high).for_each(|y| {
+                    // Image rows go down, chart rows go up
+                    let pixel = ((CHART_HEIGHT - 1 - y) * HISTORY + x) * 4;
+                    image.data[pixel..pixel + 4].copy_from_slice(&[r, g, b, 255]);
+                });
+                previous = Some(y);
+            });
+        });
+}
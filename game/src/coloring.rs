@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+use cell_sim::cell::component::{COMPONENT_COUNT, COMPONENT_NAMES};
+use cell_sim::physics::World;
+
+use crate::cell_bundle::CellId;
+use crate::world_wrapper::WorldWrapper;
+
+/// Steps in the gradient used for continuous properties.
+const GRADIENT_STEPS: usize = 16;
+/// Distinct colors used for categorical properties.
+const CATEGORIES: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+pub enum ColorMode {
+    #[default]
+    Species,
+    Atp,
+    Size,
+    DominantComponent,
+    Age,
+    Lineage,
+}
+
+impl ColorMode {
+    const ALL: [ColorMode; 6] = [
+        ColorMode::Species,
+        ColorMode::Atp,
+        ColorMode::Size,
+        ColorMode::DominantComponent,
+        ColorMode::Age,
+        ColorMode::Lineage,
+    ];
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|mode| mode == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ColorMode::Species => "species",
+            ColorMode::Atp => "ATP",
+            ColorMode::Size => "size",
+            ColorMode::DominantComponent => "most expressed component",
+            ColorMode::Age => "age",
+            ColorMode::Lineage => "lineage",
+        }
+    }
+
+    /// Continuous value of a cell for gradient modes, `None` for categorical
+    /// ones.
+    fn value(&self, world: &World, index: usize) -> Option<f32> {
+        let cell = &world.cells[index].as_ref()?.inner;
+        match self {
+            ColorMode::Atp => Some(cell.inner.chemicals.atp),
+            ColorMode::Size => Some(cell.size()),
+            ColorMode::Age => Some(cell.age),
+            _ => None,
+        }
+    }
+
+    /// Category of a cell for categorical modes, `None` for gradient ones.
+    fn category(&self, world: &World, index: usize) -> Option<usize> {
+        let cell_wrapper = world.cells[index].as_ref()?;
+        match self {
+            ColorMode::Species => {
+                Some((cell_wrapper.inner.genome.fingerprint() % CATEGORIES as u64) as usize)
+            }
+            ColorMode::Lineage => Some((cell_wrapper.founder % CATEGORIES as u64) as usize),
+            ColorMode::DominantComponent => Some(
+                cell_wrapper
+                    .inner
+                    .components
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, props)| Some((index, props.as_ref()?.expression)))
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map_or(COMPONENT_COUNT, |(index, _)| index),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// One shared material per color, so recoloring a cell only swaps handles.
+#[derive(Resource)]
+pub struct Palette {
+    gradient: Vec<Handle<ColorMaterial>>,
+    categories: Vec<Handle<ColorMaterial>>,
+    /// Used for cells without a category, e.g. no components at all.
+    none: Handle<ColorMaterial>,
+}
+
+impl Palette {
+    /// Blue at 0 through to red at 1.
+    pub fn gradient_color(t: f32) -> Color {
+        Color::hsl(240. * (1. - t), 0.8, 0.5)
+    }
+
+    pub fn category_color(category: usize) -> Color {
+        Color::hsl(360. * category as f32 / CATEGORIES as f32, 0.7, 0.55)
+    }
+
+    pub fn default_material(&self) -> Handle<ColorMaterial> {
+        self.none.clone()
+    }
+
+    fn gradient(&self, t: f32) -> &Handle<ColorMaterial> {
+        let step = (t.clamp(0., 1.) * (GRADIENT_STEPS - 1) as f32).round() as usize;
+        &self.gradient[step]
+    }
+
+    fn category(&self, category: usize) -> &Handle<ColorMaterial> {
+        self.categories.get(category).unwrap_or(&self.none)
+    }
+}
+
+impl FromWorld for Palette {
+    fn from_world(world: &mut bevy::ecs::world::World) -> Self {
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        let gradient = (0..GRADIENT_STEPS)
+            .map(|step| {
+                let t = step as f32 / (GRADIENT_STEPS - 1) as f32;
+                materials.add(ColorMaterial::from(Palette::gradient_color(t)))
+            })
+            .collect();
+        let categories = (0..CATEGORIES)
+            .map(|category| materials.add(ColorMaterial::from(Palette::category_color(category))))
+            .collect();
+
+        Self {
+            gradient,
+            categories,
+            none: materials.add(ColorMaterial::from(Color::GRAY)),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Legend;
+
+pub fn cycle_color_mode(keyboard_input: Res<Input<KeyCode>>, mut mode: ResMut<ColorMode>) {
+    if keyboard_input.just_pressed(KeyCode::C) {
+        *mode = mode.next();
+    }
+}
+
+pub fn spawn_legend(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle::default()).with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        }),
+        Legend,
+    ));
+}
+
+/// Largest value of the current gradient property over all living cells,
+/// used to scale the gradient. Found once per frame by `measure_gradient`.
+#[derive(Default, Resource)]
+pub struct GradientMax(pub f32);
+
+pub fn measure_gradient(
+    world_wrapper: Res<WorldWrapper>,
+    mode: Res<ColorMode>,
+    mut gradient_max: ResMut<GradientMax>,
+) {
+    let world = world_wrapper.world();
+    gradient_max.0 = world
+        .cells
+        .iter()
+        .flatten()
+        .filter_map(|cell_wrapper| mode.value(world, cell_wrapper.index))
+        .fold(0., f32::max);
+}
+
+pub fn color_cells(
+    world_wrapper: Res<WorldWrapper>,
+    mode: Res<ColorMode>,
+    gradient_max: Res<GradientMax>,
+    palette: Res<Palette>,
+    mut cell_bundles: Query<(&CellId, &ViewVisibility, &mut Handle<ColorMaterial>)>,
+) {
+    let world = world_wrapper.world();
+    let max = gradient_max.0.max(f32::EPSILON);
+    cell_bundles
+        .iter_mut()
+        .filter(|(_, visibility, _)| visibility.get())
+        .for_each(|(cell_id, _, mut material)| {
+            let index = cell_id.cell_id;
+            let handle = match (mode.value(world, index), mode.category(world, index)) {
+                (Some(value), _) => palette.gradient(value / max),
+                (_, Some(category)) => palette.category(category),
+                _ => return,
+            };
+            if *material != *handle {
+                *material = handle.clone();
+            }
+        });
+}
+
+pub fn update_legend(
+    mode: Res<ColorMode>,
+    gradient_max: Res<GradientMax>,
+    mut legend_query: Query<&mut Text, With<Legend>>,
+) {
+    let mut text = legend_query.single_mut();
+    let section = |value: String, color: Color| {
+        TextSection::new(
+            value,
+            TextStyle {
+                font_size: 14.,
+                color,
+                ..default()
+            },
+        )
+    };
+
+    let mut sections = vec![section(
+        format!("Color by {} (C to change)\n", mode.name()),
+        Color::WHITE,
+    )];
+    match *mode {
+        ColorMode::Atp | ColorMode::Size | ColorMode::Age => {
+            sections.push(section("0 ".to_string(), Palette::gradient_color(0.)));
+            sections.push(section("to ".to_string(), Palette::gradient_color(0.5)));
+            sections.push(section(format!("{:.2}", gradient_max.0), Palette::gradient_color(1.)));
+        }
+        ColorMode::DominantComponent => {
+            COMPONENT_NAMES.iter().enumerate().for_each(|(index, name)| {
+                sections.push(section(format!("{}\n", name), Palette::category_color(index)));
+            });
+        }
+        ColorMode::Species | ColorMode::Lineage => {
+            sections.push(section(
+                format!("{} colors, shared by unrelated groups", CATEGORIES),
+                Color::WHITE,
+            ));
+        }
+    }
+    text.sections = sections;
+}
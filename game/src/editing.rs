@@ -0,0 +1,266 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use cell_sim::cell::chemicals::Chemicals;
+use cell_sim::cell::genetics::genome::Genome;
+use cell_sim::cell::Cell;
+use nalgebra::vector;
+
+use crate::cell_bundle::CellMesh;
+use crate::coloring::Palette;
+use crate::inspector::Selection;
+use crate::overlay::FieldOverlay;
+use crate::world_wrapper::WorldWrapper;
+
+/// Glucose painted per second of holding the mouse on a tile.
+const GLUCOSE_PER_SECOND: f32 = 5.;
+const MIN_BRUSH_RADIUS: f32 = 5.;
+const MAX_BRUSH_RADIUS: f32 = 1000.;
+
+/// What a left click does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tool {
+    /// Select a cell for the inspector and drag it around.
+    #[default]
+    Select,
+    SpawnRandom,
+    /// Spawn a cell with the genome saved with K.
+    SpawnSaved,
+    Delete,
+    Glucose,
+    Light,
+    Shade,
+}
+
+impl Tool {
+    const ALL: [Tool; 7] = [
+        Tool::Select,
+        Tool::SpawnRandom,
+        Tool::SpawnSaved,
+        Tool::Delete,
+        Tool::Glucose,
+        Tool::Light,
+        Tool::Shade,
+    ];
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|tool| tool == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Tool::Select => "select and drag",
+            Tool::SpawnRandom => "spawn random cell",
+            Tool::SpawnSaved => "spawn saved genome",
+            Tool::Delete => "delete cells",
+            Tool::Glucose => "paint glucose",
+            Tool::Light => "paint light",
+            Tool::Shade => "paint shade",
+        }
+    }
+
+    fn uses_brush(&self) -> bool {
+        matches!(
+            self,
+            Tool::Delete | Tool::Glucose | Tool::Light | Tool::Shade
+        )
+    }
+}
+
+#[derive(Resource)]
+pub struct EditTools {
+    pub tool: Tool,
+    pub brush_radius: f32,
+    pub saved_genome: Option<Genome>,
+    /// Offset from the cursor to the centre of the cell being dragged.
+    grab_offset: Option<Vec2>,
+    /// Where the last left click landed, kept until the world is home to
+    /// act on it.
+    pub click: Option<Vec2>,
+    save_requested: bool,
+}
+
+impl Default for EditTools {
+    fn default() -> Self {
+        Self {
+            tool: Tool::default(),
+            brush_radius: 50.,
+            saved_genome: None,
+            grab_offset: None,
+            click: None,
+            save_requested: false,
+        }
+    }
+}
+
+/// Run condition for systems that only apply to the select tool.
+pub fn selecting(tools: Res<EditTools>) -> bool {
+    tools.tool == Tool::Select
+}
+
+/// World position under the mouse cursor, if it is over the window.
+pub fn cursor_position(
+    window_query: &Query<&Window, With<PrimaryWindow>>,
+    camera_query: &Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+) -> Option<Vec2> {
+    let (camera, camera_transform) = camera_query.single();
+    window_query
+        .single()
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+}
+
+#[derive(Component)]
+pub struct ToolText;
+
+pub fn spawn_tool_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 14.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.),
+            left: Val::Percent(40.),
+            ..default()
+        }),
+        ToolText,
+    ));
+}
+
+/// T cycles the tool, [ and ] shrink and grow the brush and K saves the
+/// selected cell's genome for `Tool::SpawnSaved`. Saving and clicks wait for
+/// `apply_tool`.
+pub fn tool_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut tools: ResMut<EditTools>,
+) {
+    if mouse.just_pressed(MouseButton::Left) {
+        tools.click = cursor_position(&window_query, &camera_query);
+    }
+    if keyboard_input.just_pressed(KeyCode::T) {
+        tools.tool = tools.tool.next();
+        tools.grab_offset = None;
+    }
+    if keyboard_input.just_pressed(KeyCode::BracketLeft) {
+        tools.brush_radius = (tools.brush_radius / 1.5).max(MIN_BRUSH_RADIUS);
+    }
+    if keyboard_input.just_pressed(KeyCode::BracketRight) {
+        tools.brush_radius = (tools.brush_radius * 1.5).min(MAX_BRUSH_RADIUS);
+    }
+    if keyboard_input.just_pressed(KeyCode::K) {
+        tools.save_requested = true;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn apply_tool(
+    mouse: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    selection: Res<Selection>,
+    cell_mesh: Res<CellMesh>,
+    palette: Res<Palette>,
+    mut tools: ResMut<EditTools>,
+    mut overlay: ResMut<FieldOverlay>,
+    mut world_wrapper: ResMut<WorldWrapper>,
+    mut commands: Commands,
+    mut gizmos: Gizmos,
+) {
+    let click = tools.click.take();
+    if !mouse.pressed(MouseButton::Left) {
+        tools.grab_offset = None;
+    }
+    if std::mem::take(&mut tools.save_requested) {
+        let world = world_wrapper.world();
+        if let Some(index) = selection.get(world) {
+            tools.saved_genome = Some(world.cells[index].as_ref().unwrap().inner.genome);
+        }
+    }
+
+    // Clicks may have happened while the world was away, so spawning uses
+    // where the click landed rather than the cursor
+    if let Some(click) = click {
+        if matches!(tools.tool, Tool::SpawnRandom | Tool::SpawnSaved) {
+            let mut cell = Cell::new_random();
+            if tools.tool == Tool::SpawnSaved {
+                let Some(genome) = tools.saved_genome else {
+                    return;
+                };
+                cell.genome = genome;
+            }
+            world_wrapper.add_cell(cell, click, &mut commands, &cell_mesh, &palette);
+            world_wrapper.world_mut().refresh_queries();
+            return;
+        }
+    }
+    let Some(cursor) = cursor_position(&window_query, &camera_query) else {
+        return;
+    };
+    if tools.tool.uses_brush() {
+        gizmos.circle_2d(cursor, tools.brush_radius, Color::WHITE);
+    }
+    if !mouse.pressed(MouseButton::Left) {
+        return;
+    }
+
+    let point = vector![cursor.x, cursor.y];
+    let radius = tools.brush_radius;
+    let world = world_wrapper.world_mut();
+    match tools.tool {
+        Tool::Select => {
+            let Some(index) = selection.get(world) else {
+                return;
+            };
+            if let Some(click) = click {
+                let position = world.cell_position(index).unwrap();
+                tools.grab_offset = Some(Vec2::new(position.x, position.y) - click);
+            }
+            if let Some(offset) = tools.grab_offset {
+                let target = cursor + offset;
+                world.move_cell(index, vector![target.x, target.y]);
+            }
+        }
+        Tool::SpawnRandom | Tool::SpawnSaved => (),
+        Tool::Delete => {
+            world.remove_cells_within(point, radius);
+        }
+        Tool::Glucose => {
+            let chemicals = Chemicals {
+                glucose: GLUCOSE_PER_SECOND * time.delta_seconds(),
+                ..default()
+            };
+            world.paint_chemicals(point, radius, chemicals);
+            overlay.redraw();
+        }
+        Tool::Light => {
+            world.paint_light(point, radius, 1.);
+            overlay.redraw();
+        }
+        Tool::Shade => {
+            world.paint_light(point, radius, 0.);
+            overlay.redraw();
+        }
+    }
+}
+
+pub fn update_tool_text(tools: Res<EditTools>, mut text_query: Query<&mut Text, With<ToolText>>) {
+    let saved = match tools.saved_genome {
+        Some(genome) => format!("saved genome {:x}", genome.fingerprint()),
+        None => "no saved genome".to_string(),
+    };
+    text_query.single_mut().sections[0].value = format!(
+        "Tool: {} (T to change), brush {:.0} ([ and ]), {} (K to save)",
+        tools.tool.name(),
+        tools.brush_radius,
+        saved
+    );
+}
@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use cell_sim::cell::component::COMPONENT_NAMES;
+use cell_sim::physics::World;
+use nalgebra::vector;
+
+use crate::editing::EditTools;
+use crate::world_wrapper::WorldWrapper;
+
+/// Cell shown in the inspector, as its index and its id so a dead cell whose
+/// index got reused is not mistaken for the selected one.
+#[derive(Default, Resource)]
+pub struct Selection {
+    pub cell: Option<(usize, u64)>,
+}
+
+impl Selection {
+    /// Index of the selected cell if it is still alive.
+    pub fn get(&self, world: &World) -> Option<usize> {
+        let (index, id) = self.cell?;
+        let cell_wrapper = world.cells.get(index)?.as_ref()?;
+        (cell_wrapper.id == id).then_some(index)
+    }
+}
+
+#[derive(Component)]
+pub struct InspectorPanel;
+
+#[derive(Component)]
+pub struct InspectorText;
+
+pub fn spawn_inspector(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.),
+                    right: Val::Px(10.),
+                    padding: UiRect::all(Val::Px(8.)),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                ..default()
+            },
+            InspectorPanel,
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 14.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                InspectorText,
+            ));
+        });
+}
+
+/// Selects the cell under the last left click, or clears the selection when
+/// clicking empty space.
+pub fn select_cell(
+    tools: Res<EditTools>,
+    world_wrapper: Res<WorldWrapper>,
+    mut selection: ResMut<Selection>,
+) {
+    let Some(position) = tools.click else {
+        return;
+    };
+
+    let world = world_wrapper.world();
+    selection.cell = world
+        .cell_at(vector![position.x, position.y])
+        .and_then(|index| Some((index, world.cells[index].as_ref()?.id)));
+}
+
+pub fn update_inspector(
+    world_wrapper: Res<WorldWrapper>,
+    mut selection: ResMut<Selection>,
+    mut panel_query: Query<&mut Style, With<InspectorPanel>>,
+    mut text_query: Query<&mut Text, With<InspectorText>>,
+    mut gizmos: Gizmos,
+) {
+    let world = world_wrapper.world();
+    let mut style = panel_query.single_mut();
+    let Some(index) = selection.get(world) else {
+        selection.cell = None;
+        style.display = Display::None;
+        return;
+    };
+    style.display = Display::Flex;
+    text_query.single_mut().sections[0].value = describe(world, index);
+
+    if let Some(position) = world.cell_position(index) {
+        let radius = world.cells[index].as_ref().unwrap().inner.size();
+        gizmos.circle_2d(Vec2::new(position.x, position.y), radius * 1.2, Color::YELLOW);
+    }
+}
+
+/// Inspector text for the cell at `index`.
+fn describe(world: &World, index: usize) -> String {
+    let cell_wrapper = world.cells[index].as_ref().unwrap();
+    let cell = &cell_wrapper.inner;
+    let inner = &cell.inner;
+    let velocity = world.rigid_body_set[cell_wrapper.rigid_body_handle].linvel();
+
+    let parent = cell_wrapper
+        .parent
+        .map_or("none".to_string(), |parent| parent.to_string());
+    let mut lines = vec![
+        format!("Cell {} (id {})", index, cell_wrapper.id),
+        format!("Type {}, generation {}", cell.cell_type, cell.generation),
+        format!("Parent {}, founder {}", parent, cell_wrapper.founder),
+        format!("Age {:.2}, size {:.2}", cell.age, cell.size()),
+        format!("Velocity ({:.2}, {:.2})", velocity.x, velocity.y),
+        format!(
+            "ATP {:.3}, glucose {:.3}, waste {:.3}",
+            inner.chemicals.atp, inner.chemicals.glucose, inner.chemicals.waste
+        ),
+        format!("Proteins {:.3}, nucleotides {:.3}", inner.proteins, inner.nucleotides),
+        format!("Water {:.3}, pH {:.2}", inner.water, inner.ph),
+    ];
+    lines.extend(
+        COMPONENT_NAMES
+            .iter()
+            .zip(cell.components.iter())
+            .filter_map(|(name, component)| {
+                let props = component.as_ref()?;
+                Some(format!(
+                    "  {}: expression {:.2}, proteins {:.2}/{:.2}, speed {:.2}, efficiency {:.2}",
+                    name,
+                    props.expression,
+                    props.proteins(),
+                    props.target_proteins(),
+                    props.speed,
+                    props.efficiency
+                ))
+            }),
+    );
+
+    lines.join("\n")
+}
@@ -0,0 +1,11 @@
+pub mod cell_bundle;
+pub mod charts;
+pub mod coloring;
+pub mod editing;
+pub mod inspector;
+pub mod overlay;
+mod plugin;
+pub mod scene;
+pub mod time_controls;
+pub mod world_wrapper;
+pub use plugin::{world_home, CellSimPlugin, CellSimSettings, RenderMode, SimSet, SimTask};
@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use cell_sim::physics::Environment;
+
+use crate::coloring::Palette;
+use crate::world_wrapper::WorldWrapper;
+
+/// Environment field drawn under the cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Field {
+    #[default]
+    Off,
+    Glucose,
+    Atp,
+    Waste,
+    Signal,
+    Light,
+}
+
+impl Field {
+    const ALL: [Field; 6] = [
+        Field::Off,
+        Field::Glucose,
+        Field::Atp,
+        Field::Waste,
+        Field::Signal,
+        Field::Light,
+    ];
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|field| field == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Field::Off => "off",
+            Field::Glucose => "glucose",
+            Field::Atp => "ATP",
+            Field::Waste => "waste",
+            Field::Signal => "signal",
+            Field::Light => "light",
+        }
+    }
+
+    fn values(&self, environment: &Environment) -> Vec<f32> {
+        let tiles = environment.tiles().iter();
+        match self {
+            Field::Off => Vec::new(),
+            Field::Glucose => tiles.map(|tile| tile.glucose).collect(),
+            Field::Atp => tiles.map(|tile| tile.atp).collect(),
+            Field::Waste => tiles.map(|tile| tile.waste).collect(),
+            Field::Signal => tiles.map(|tile| tile.signal).collect(),
+            Field::Light => environment.light_map().to_vec(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct FieldOverlay {
+    pub field: Field,
+    image: Handle<Image>,
+    /// Field and world time the image was last drawn for.
+    drawn: Option<(Field, f64)>,
+}
+
+impl FieldOverlay {
+    /// Draws the image again on the next frame, for changes to the
+    /// environment between ticks such as painting.
+    pub fn redraw(&mut self) {
+        self.drawn = None;
+    }
+}
+
+#[derive(Component)]
+pub struct OverlaySprite;
+
+#[derive(Component)]
+pub struct OverlayLegend;
+
+/// Creates a texture with one pixel per environment tile, stretched over the
+/// environment behind the cells.
+pub fn spawn_overlay(
+    mut commands: Commands,
+    world_wrapper: Res<WorldWrapper>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let environment = &world_wrapper.world().environment;
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: environment.width() as u32,
+            height: environment.height() as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::nearest();
+    let image = images.add(image);
+
+    let size = environment.size();
+    let center = environment.origin() + size / 2.;
+    commands.spawn((
+        SpriteBundle {
+            texture: image.clone(),
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(size.x, size.y)),
+                ..default()
+            },
+            transform: Transform::from_xyz(center.x, center.y, -1.),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        OverlaySprite,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 14.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.),
+            right: Val::Px(10.),
+            ..default()
+        }),
+        OverlayLegend,
+    ));
+    commands.insert_resource(FieldOverlay {
+        field: Field::Off,
+        image,
+        drawn: None,
+    });
+}
+
+pub fn cycle_field(keyboard_input: Res<Input<KeyCode>>, mut overlay: ResMut<FieldOverlay>) {
+    if keyboard_input.just_pressed(KeyCode::F) {
+        overlay.field = overlay.field.next();
+    }
+}
+
+/// Draws the selected field whenever it changes or the world has moved on
+/// since it was last drawn.
+pub fn update_overlay(
+    world_wrapper: Res<WorldWrapper>,
+    mut overlay: ResMut<FieldOverlay>,
+    mut images: ResMut<Assets<Image>>,
+    mut sprite_query: Query<&mut Visibility, With<OverlaySprite>>,
+    mut legend_query: Query<&mut Text, With<OverlayLegend>>,
+) {
+    let mut visibility = sprite_query.single_mut();
+    let mut legend = legend_query.single_mut();
+    if overlay.field == Field::Off {
+        *visibility = Visibility::Hidden;
+        legend.sections[0].value = "Field overlay off (F to change)".to_string();
+        overlay.drawn = None;
+        return;
+    }
+    *visibility = Visibility::Visible;
+
+    let world = world_wrapper.world();
+    let drawn = Some((overlay.field, world.time()));
+    if overlay.drawn == drawn {
+        return;
+    }
+    overlay.drawn = drawn;
+    let environment = &world.environment;
+    let values = overlay.field.values(environment);
+    let max = values.iter().copied().fold(0., f32::max);
+    legend.sections[0].value = format!(
+        "Field {}: 0 (blue) to {:.3} (red) (F to change)",
+        overlay.field.name(),
+        max
+    );
+
+    let Some(image) = images.get_mut(&overlay.image) else {
+        return;
+    };
+    let (width, height) = (environment.width(), environment.height());
+    values.iter().enumerate().for_each(|(index, value)| {
+        let (x, y) = (index % width, index / width);
+        // Image rows go down, environment rows go up
+        let pixel = ((height - 1 - y) * width + x) * 4;
+        let [r, g, b, _] = Palette::gradient_color(value / max.max(f32::EPSILON)).as_rgba_u8();
+        image.data[pixel..pixel + 4].copy_from_slice(&[r, g, b, 160]);
+    });
+}
@@ -0,0 +1,337 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use cell_sim::physics::World;
+
+use crate::cell_bundle::CellMesh;
+use crate::charts::{record_statistics, spawn_charts, toggle_charts, update_charts};
+use crate::coloring::{
+    color_cells, cycle_color_mode, measure_gradient, spawn_legend, update_legend, ColorMode,
+    GradientMax, Palette,
+};
+use crate::editing::{
+    apply_tool, selecting, spawn_tool_text, tool_input, update_tool_text, EditTools,
+};
+use crate::inspector::{select_cell, spawn_inspector, update_inspector, Selection};
+use crate::overlay::{cycle_field, spawn_overlay, update_overlay};
+use crate::scene::{cycle_camera_follow, follow_camera, move_camera, spawn_camera, CameraFollow};
+use crate::time_controls::{
+    apply_time_input, spawn_time_display, time_input, update_time_display, TimeControls,
+};
+use crate::world_wrapper::{populate, spawn_cell_entities, step_world, sync_cells, WorldWrapper};
+
+/// How much of the simulation the plugin draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// Cells plus the HUD: legends, inspector, editing tools, time controls
+    /// and charts.
+    #[default]
+    Full,
+    /// Cells and a camera only, for apps bringing their own UI.
+    CellsOnly,
+    /// Nothing drawn, only the world is simulated.
+    Headless,
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct CellSimSettings {
+    /// Scenario file applied at startup, see `Scenario::load`.
+    pub config_path: Option<PathBuf>,
+    /// Seeds the initial cells and where they are placed.
+    pub seed: Option<u64>,
+    pub initial_cells: usize,
+    /// Size of the rectangle from the origin the initial cells are placed in.
+    pub spawn_area: Vec2,
+    pub render_mode: RenderMode,
+    /// Advance the world on a background task instead of in the frame. While
+    /// the task runs `WorldWrapper` has no world, so systems reading it belong
+    /// in `SimSet::Input` to `SimSet::Ui` or should check `world_home`. Input meant for the world is buffered in `SimSet::Controls`
+    /// until then.
+    pub background: bool,
+}
+
+impl Default for CellSimSettings {
+    fn default() -> Self {
+        Self {
+            config_path: None,
+            seed: None,
+            initial_cells: 20000,
+            spawn_area: Vec2::new(10000., 1200.),
+            render_mode: RenderMode::default(),
+            background: false,
+        }
+    }
+}
+
+/// Order of the plugin's systems within `Update`. Everything after `Controls`
+/// only runs while the world is not away on a background task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
+pub enum SimSet {
+    /// Advances the world, or collects it from the background task.
+    Step,
+    /// Keyboard and mouse input, which runs every frame so no press is
+    /// missed. Presses that change the world are kept for `Input`.
+    Controls,
+    /// Applies the input that changes the world.
+    Input,
+    /// Spawns, despawns and moves cell entities.
+    Sync,
+    /// Colors, overlays and the camera.
+    Visuals,
+    /// Text panels and charts.
+    Ui,
+    /// Sends the world off to the next background task.
+    Launch,
+}
+
+/// Runs the simulation and draws it, see `CellSimSettings`.
+#[derive(Default)]
+pub struct CellSimPlugin {
+    pub settings: CellSimSettings,
+}
+
+/// World being advanced in the background, with the real time that passed
+/// since it was sent off.
+#[derive(Default, Resource)]
+pub struct SimTask {
+    task: Option<Task<World>>,
+    pending: f32,
+}
+
+/// Run condition for systems that need the world, which is away while a
+/// background task has it.
+pub fn world_home(world_wrapper: Res<WorldWrapper>) -> bool {
+    world_wrapper.is_home()
+}
+
+fn collect_world_task(
+    mut sim_task: ResMut<SimTask>,
+    mut world_wrapper: ResMut<WorldWrapper>,
+    time: Res<Time>,
+) {
+    sim_task.pending += time.delta_seconds();
+    if !sim_task.task.as_ref().is_some_and(Task::is_finished) {
+        return;
+    }
+    let task = sim_task.task.take().unwrap();
+    world_wrapper.restore_world(block_on(task));
+}
+
+fn launch_world_task(
+    mut sim_task: ResMut<SimTask>,
+    mut world_wrapper: ResMut<WorldWrapper>,
+    mut controls: ResMut<TimeControls>,
+) {
+    if sim_task.task.is_some() {
+        return;
+    }
+    let dt = std::mem::take(&mut sim_task.pending);
+    let mut world = world_wrapper.take_world();
+    let advance = controls.plan(world.scheduler.paused, dt);
+    sim_task.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        advance.run(&mut world);
+        world
+    }));
+}
+
+impl Plugin for CellSimPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = self.settings.clone();
+        app.insert_resource(settings.clone())
+            .init_resource::<WorldWrapper>()
+            .init_resource::<TimeControls>()
+            .init_resource::<SimTask>()
+            .configure_sets(
+                Update,
+                (
+                    SimSet::Step,
+                    SimSet::Controls,
+                    SimSet::Input,
+                    SimSet::Sync,
+                    SimSet::Visuals,
+                    SimSet::Ui,
+                    SimSet::Launch,
+                )
+                    .chain(),
+            )
+            .configure_sets(
+                Update,
+                (SimSet::Input, SimSet::Sync, SimSet::Visuals, SimSet::Ui).run_if(world_home),
+            )
+            .add_systems(Startup, populate);
+        if settings.background {
+            app.add_systems(Update, collect_world_task.in_set(SimSet::Step))
+                .add_systems(Update, launch_world_task.in_set(SimSet::Launch));
+        } else {
+            app.add_systems(Update, step_world.in_set(SimSet::Step));
+        }
+        if settings.render_mode == RenderMode::Headless {
+            return;
+        }
+
+        app.init_resource::<CellMesh>()
+            .init_resource::<Palette>()
+            .init_resource::<ColorMode>()
+            .init_resource::<GradientMax>()
+            .init_resource::<Selection>()
+            .init_resource::<CameraFollow>()
+            .add_systems(Startup, (spawn_camera, spawn_cell_entities.after(populate)))
+            .add_systems(Update, sync_cells.in_set(SimSet::Sync))
+            .add_systems(
+                Update,
+                (measure_gradient, color_cells).chain().in_set(SimSet::Visuals),
+            )
+            // Panning the camera does not need the world
+            .add_systems(
+                Update,
+                move_camera.after(SimSet::Input).before(SimSet::Visuals),
+            )
+            .add_systems(
+                Update,
+                follow_camera.in_set(SimSet::Visuals).after(move_camera),
+            );
+        if settings.render_mode == RenderMode::CellsOnly {
+            return;
+        }
+
+        app.init_resource::<EditTools>()
+            .add_systems(
+                Startup,
+                (
+                    spawn_legend,
+                    spawn_overlay.after(populate),
+                    spawn_inspector,
+                    spawn_tool_text,
+                    spawn_time_display,
+                    spawn_charts,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    cycle_color_mode,
+                    cycle_field,
+                    toggle_charts,
+                    cycle_camera_follow,
+                    time_input,
+                    tool_input,
+                )
+                    .in_set(SimSet::Controls),
+            )
+            .add_systems(
+                Update,
+                (
+                    apply_time_input,
+                    (select_cell.run_if(selecting), apply_tool).chain(),
+                )
+                    .in_set(SimSet::Input),
+            )
+            .add_systems(
+                Update,
+                (update_overlay, record_statistics).in_set(SimSet::Visuals),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_legend,
+                    update_inspector,
+                    update_tool_text,
+                    update_time_display,
+                    update_charts,
+                )
+                    .in_set(SimSet::Ui),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Largest tick count and population seen from inside the gated sets.
+    #[derive(Default, Resource)]
+    struct Seen(u64, usize);
+
+    fn record(world_wrapper: Res<WorldWrapper>, mut seen: ResMut<Seen>) {
+        let world = world_wrapper.world();
+        seen.0 = seen.0.max(world.scheduler.ticks());
+        seen.1 = world.statistics.population;
+    }
+
+    #[test]
+    fn test_headless_background() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugins(CellSimPlugin {
+            settings: CellSimSettings {
+                initial_cells: 10,
+                seed: Some(0),
+                render_mode: RenderMode::Headless,
+                background: true,
+                ..default()
+            },
+        });
+        // Outside the gated sets the world may be away on its task
+        app.init_resource::<Seen>()
+            .add_systems(Update, record.in_set(SimSet::Ui));
+
+        // The first frame sends the world off
+        app.update();
+        assert!(!app.world.resource::<WorldWrapper>().is_home());
+
+        let start = std::time::Instant::now();
+        while app.world.resource::<Seen>().0 == 0
+            && start.elapsed() < std::time::Duration::from_secs(10)
+        {
+            app.update();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        let seen = app.world.resource::<Seen>();
+        assert!(seen.0 > 0);
+        assert_eq!(seen.1, 10);
+    }
+
+    #[derive(Default, Resource)]
+    struct Paused(bool);
+
+    fn record_paused(world_wrapper: Res<WorldWrapper>, mut paused: ResMut<Paused>) {
+        paused.0 = world_wrapper.world().scheduler.paused;
+    }
+
+    #[test]
+    fn test_background_input() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugins(CellSimPlugin {
+            settings: CellSimSettings {
+                initial_cells: 10,
+                seed: Some(0),
+                render_mode: RenderMode::Headless,
+                background: true,
+                ..default()
+            },
+        });
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<Paused>()
+            .add_systems(Update, time_input.in_set(SimSet::Controls))
+            .add_systems(Update, apply_time_input.in_set(SimSet::Input))
+            .add_systems(Update, record_paused.in_set(SimSet::Ui));
+        // Each task keeps the world away for the whole fast budget, so the
+        // press lands while it is gone
+        app.world.resource_mut::<TimeControls>().fast = true;
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Space);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().clear();
+
+        let start = std::time::Instant::now();
+        while !app.world.resource::<Paused>().0
+            && start.elapsed() < std::time::Duration::from_secs(10)
+        {
+            app.update();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        assert!(app.world.resource::<Paused>().0);
+    }
+}
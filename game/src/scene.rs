@@ -1,6 +1,41 @@
-use bevy::input::mouse::MouseWheel;
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
+use nalgebra::Vector2;
+
+use crate::inspector::Selection;
+use crate::world_wrapper::WorldWrapper;
+
+/// Screen pixels per second panned with WASD, whatever the zoom.
+const PAN_SPEED: f32 = 600.;
+/// Zoom factor per scroll wheel line.
+const ZOOM_STEP: f32 = 1.1;
+/// Pixel scroll deltas per wheel line, for touchpads.
+const PIXELS_PER_LINE: f32 = 20.;
+const MIN_SCALE: f32 = 0.05;
+const MAX_SCALE: f32 = 50.;
+/// How quickly the camera catches up with what it follows, per second.
+const FOLLOW_RATE: f32 = 5.;
+
+/// What the camera tracks, taken from the inspector's selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+pub enum CameraFollow {
+    #[default]
+    Off,
+    Cell,
+    /// Centre of the selected cell's colony.
+    Colony,
+}
+
+impl CameraFollow {
+    pub fn next(&self) -> Self {
+        match self {
+            CameraFollow::Off => CameraFollow::Cell,
+            CameraFollow::Cell => CameraFollow::Colony,
+            CameraFollow::Colony => CameraFollow::Off,
+        }
+    }
+}
 
 pub fn spawn_camera(mut commands: Commands, window_query: Query<&Window, With<PrimaryWindow>>) {
     let window = window_query.single();
@@ -11,23 +46,102 @@ pub fn spawn_camera(mut commands: Commands, window_query: Query<&Window, With<Pr
     });
 }
 
+/// WASD and middle mouse drag pan, the scroll wheel zooms towards the cursor.
+/// Panning by hand stops following.
+#[allow(clippy::too_many_arguments)]
 pub fn move_camera(
     mut query: Query<&mut Transform, With<Camera2d>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
     keyboard_input: Res<Input<KeyCode>>,
-    mut mouse_wheel: ResMut<Events<MouseWheel>>,
+    mouse: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut follow: ResMut<CameraFollow>,
 ) {
     let mut transform = query.single_mut();
-    keyboard_input.get_pressed().for_each(|key| {
-        match key {
-            KeyCode::W => transform.translation.y += 10.,
-            KeyCode::S => transform.translation.y -= 10.,
-            KeyCode::A => transform.translation.x -= 10.,
-            KeyCode::D => transform.translation.x += 10.,
-            _ => (),
-        }
-    });
+    let window = window_query.single();
+    let scale = transform.scale.x;
 
-    mouse_wheel.drain().for_each(|e| {
-        transform.scale += -e.y * 0.05;
+    let mut direction = Vec2::ZERO;
+    keyboard_input.get_pressed().for_each(|key| match key {
+        KeyCode::W => direction.y += 1.,
+        KeyCode::S => direction.y -= 1.,
+        KeyCode::A => direction.x -= 1.,
+        KeyCode::D => direction.x += 1.,
+        _ => (),
     });
+    let mut pan = direction.normalize_or_zero() * PAN_SPEED * time.delta_seconds() * scale;
+    if mouse.pressed(MouseButton::Middle) {
+        // Screen y points down
+        pan += mouse_motion
+            .read()
+            .fold(Vec2::ZERO, |pan, motion| pan + motion.delta * Vec2::new(-1., 1.) * scale);
+    } else {
+        mouse_motion.clear();
+    }
+    if pan != Vec2::ZERO {
+        transform.translation += pan.extend(0.);
+        *follow = CameraFollow::Off;
+    }
+
+    let lines: f32 = mouse_wheel
+        .read()
+        .map(|wheel| match wheel.unit {
+            MouseScrollUnit::Line => wheel.y,
+            MouseScrollUnit::Pixel => wheel.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    if lines != 0. {
+        let new_scale = (scale * ZOOM_STEP.powf(-lines)).clamp(MIN_SCALE, MAX_SCALE);
+        // Keep the point under the cursor where it is
+        if let Some(cursor) = window.cursor_position() {
+            let offset = (cursor - Vec2::new(window.width(), window.height()) / 2.)
+                * Vec2::new(1., -1.);
+            let shift = offset * (scale - new_scale);
+            transform.translation += shift.extend(0.);
+        }
+        transform.scale = Vec3::new(new_scale, new_scale, 1.);
+    }
+}
+
+/// V cycles between not following, following the selected cell and
+/// following its colony.
+pub fn cycle_camera_follow(keyboard_input: Res<Input<KeyCode>>, mut follow: ResMut<CameraFollow>) {
+    if keyboard_input.just_pressed(KeyCode::V) {
+        *follow = follow.next();
+    }
+}
+
+pub fn follow_camera(
+    mut query: Query<&mut Transform, With<Camera2d>>,
+    time: Res<Time>,
+    world_wrapper: Res<WorldWrapper>,
+    selection: Res<Selection>,
+    follow: Res<CameraFollow>,
+) {
+    let world = world_wrapper.world();
+    let Some(index) = selection.get(world) else {
+        return;
+    };
+    let target = match *follow {
+        CameraFollow::Off => return,
+        CameraFollow::Cell => world.cell_position(index),
+        CameraFollow::Colony => {
+            let colony = world.colony(index);
+            let sum = colony
+                .iter()
+                .filter_map(|&cell_idx| world.cell_position(cell_idx))
+                .fold(Vector2::zeros(), |sum, position| sum + position);
+            Some(sum / colony.len().max(1) as f32)
+        }
+    };
+    let Some(target) = target else {
+        return;
+    };
+
+    let mut transform = query.single_mut();
+    let t = 1. - (-FOLLOW_RATE * time.delta_seconds()).exp();
+    let target = Vec3::new(target.x, target.y, transform.translation.z);
+    transform.translation = transform.translation.lerp(target, t);
 }
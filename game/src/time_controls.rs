@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use cell_sim::physics::World;
+
+use crate::world_wrapper::WorldWrapper;
+
+/// Real time spent simulating per frame when running as fast as possible.
+/// The display only refreshes between these batches.
+const FAST_BUDGET: Duration = Duration::from_millis(50);
+/// Real seconds between ticks-per-second measurements.
+const TPS_WINDOW: f32 = 0.5;
+const MIN_SPEED: f32 = 0.125;
+const MAX_SPEED: f32 = 64.;
+
+/// How the game drives the simulation clock, on top of `Scheduler`.
+#[derive(Default, Resource)]
+pub struct TimeControls {
+    /// Ignore the clock and run as many ticks as fit in `FAST_BUDGET` each
+    /// frame.
+    pub fast: bool,
+    step_requested: bool,
+    /// Presses waiting for the world, which may be away on a background task
+    /// when they happen.
+    pending: Vec<TimeCommand>,
+    tps: f32,
+    window_ticks: u64,
+    /// Real time the current ticks-per-second window started at.
+    window_start: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeCommand {
+    TogglePause,
+    Step,
+    Faster,
+    Slower,
+}
+
+/// What to run for one frame, decided up front so it can be carried out
+/// away from the controls, e.g. on a background task.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Advance {
+    Idle,
+    Step,
+    Fast,
+    /// Follow the scheduler's clock for this many real seconds.
+    Clock(f32),
+}
+
+impl Advance {
+    pub fn run(self, world: &mut World) {
+        match self {
+            Advance::Idle => (),
+            Advance::Step => world.update(),
+            Advance::Fast => {
+                world.run_for(FAST_BUDGET);
+            }
+            Advance::Clock(dt) => world.advance(dt),
+        }
+    }
+}
+
+impl TimeControls {
+    /// What to run for a frame of `dt` real seconds.
+    pub fn plan(&mut self, paused: bool, dt: f32) -> Advance {
+        if paused {
+            match std::mem::take(&mut self.step_requested) {
+                true => Advance::Step,
+                false => Advance::Idle,
+            }
+        } else if self.fast {
+            Advance::Fast
+        } else {
+            Advance::Clock(dt)
+        }
+    }
+
+    /// Advances `world` by one frame of `dt` real seconds.
+    pub fn advance(&mut self, world: &mut World, dt: f32) {
+        self.plan(world.scheduler.paused, dt).run(world);
+    }
+
+    fn measure(&mut self, world: &World, now: f32) {
+        let elapsed = now - self.window_start;
+        if elapsed < TPS_WINDOW {
+            return;
+        }
+        let ticks = world.scheduler.ticks();
+        self.tps = (ticks - self.window_ticks) as f32 / elapsed;
+        self.window_ticks = ticks;
+        self.window_start = now;
+    }
+}
+
+#[derive(Component)]
+pub struct TimeDisplay;
+
+pub fn spawn_time_display(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 14.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.),
+            left: Val::Px(10.),
+            ..default()
+        }),
+        TimeDisplay,
+    ));
+}
+
+/// Space pauses, period steps once while paused, +/- double or halve the
+/// speed and M toggles running as fast as possible. Changes to the scheduler
+/// wait for `apply_time_input`.
+pub fn time_input(keyboard_input: Res<Input<KeyCode>>, mut controls: ResMut<TimeControls>) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        controls.pending.push(TimeCommand::TogglePause);
+    }
+    if keyboard_input.just_pressed(KeyCode::Period) {
+        controls.pending.push(TimeCommand::Step);
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Equals, KeyCode::NumpadAdd]) {
+        controls.pending.push(TimeCommand::Faster);
+        controls.fast = false;
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        controls.pending.push(TimeCommand::Slower);
+        controls.fast = false;
+    }
+    if keyboard_input.just_pressed(KeyCode::M) {
+        controls.fast = !controls.fast;
+    }
+}
+
+/// Applies the presses `time_input` saw since the world was last home.
+pub fn apply_time_input(
+    mut world_wrapper: ResMut<WorldWrapper>,
+    mut controls: ResMut<TimeControls>,
+) {
+    let scheduler = &mut world_wrapper.world_mut().scheduler;
+    let pending = std::mem::take(&mut controls.pending);
+    pending.into_iter().for_each(|command| match command {
+        TimeCommand::TogglePause => scheduler.paused = !scheduler.paused,
+        TimeCommand::Step => {
+            scheduler.paused = true;
+            controls.step_requested = true;
+        }
+        TimeCommand::Faster => scheduler.speed = (scheduler.speed * 2.).min(MAX_SPEED),
+        TimeCommand::Slower => scheduler.speed = (scheduler.speed / 2.).max(MIN_SPEED),
+    });
+}
+
+pub fn update_time_display(
+    world_wrapper: Res<WorldWrapper>,
+    time: Res<Time>,
+    mut controls: ResMut<TimeControls>,
+    mut display_query: Query<&mut Text, With<TimeDisplay>>,
+) {
+    let world = world_wrapper.world();
+    controls.measure(world, time.elapsed_seconds());
+
+    let scheduler = &world.scheduler;
+    let state = if scheduler.paused {
+        "paused".to_string()
+    } else if controls.fast {
+        "max speed".to_string()
+    } else {
+        format!("{}x", scheduler.speed)
+    };
+    display_query.single_mut().sections[0].value = format!(
+        "Tick {}, {:.1} s simulated, {:.0} ticks/s, {}\n\
+         Space pause, . step, +/- speed, M max speed, G charts, V follow cell or colony",
+        scheduler.ticks(),
+        world.time(),
+        controls.tps,
+        state
+    );
+}
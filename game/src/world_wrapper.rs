@@ -1,161 +1,232 @@
-use bevy::sprite::Mesh2dHandle;
-use bevy::window::PrimaryWindow;
-use bevy::{log, prelude::*};
+use bevy::prelude::*;
 use cell_sim::cell::Cell;
-use cell_sim::physics::World;
+use cell_sim::physics::{Scenario, World};
 use nalgebra::vector;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-use crate::cell_bundle::{CellBundle, CellId};
+use crate::cell_bundle::{CellBundle, CellId, CellMesh};
+use crate::coloring::Palette;
+use crate::plugin::CellSimSettings;
+use crate::time_controls::TimeControls;
 
-#[derive(Default, Resource)]
+#[derive(Resource)]
 pub struct WorldWrapper {
-    pub world: World,
-    #[cfg(debug_assertions)]
-    debug: Debug,
+    /// `None` while a background task is advancing the world.
+    world: Option<World>,
+    /// Entity drawing each cell index, with the id of the cell it was
+    /// spawned for, so a reused index is noticed.
+    entities: Vec<Option<(u64, Entity)>>,
 }
 
-#[derive(Default)]
-struct Debug {
-    pub world_update_time: std::time::Duration,
-    pub bevy_update_time: std::time::Duration,
-    pub bevy_find_rigid_body_time: std::time::Duration,
-    pub bevy_update_mesh_time: std::time::Duration,
-    pub bevy_update_transform_time: std::time::Duration,
-    pub frames: u32,
+impl Default for WorldWrapper {
+    fn default() -> Self {
+        Self {
+            world: Some(World::default()),
+            entities: Vec::new(),
+        }
+    }
 }
 
 impl WorldWrapper {
+    /// Whether the world is here rather than away on a background task.
+    pub fn is_home(&self) -> bool {
+        self.world.is_some()
+    }
+
+    /// Panics while the world is away, see `world_home`.
+    pub fn world(&self) -> &World {
+        self.world.as_ref().expect("the world is away on a background task")
+    }
+
+    /// Panics while the world is away, see `world_home`.
+    pub fn world_mut(&mut self) -> &mut World {
+        self.world.as_mut().expect("the world is away on a background task")
+    }
+
+    /// Hands the world to a background task until `restore_world`.
+    pub fn take_world(&mut self) -> World {
+        self.world.take().expect("the world is already away")
+    }
+
+    pub fn restore_world(&mut self, world: World) {
+        self.world = Some(world);
+    }
+
     pub fn add_cell(
         &mut self,
         cell: Cell,
         pos: Vec2,
         commands: &mut Commands,
-        meshes: &mut Assets<Mesh>,
-        color_materials: &mut Assets<ColorMaterial>,
+        cell_mesh: &CellMesh,
+        palette: &Palette,
     ) {
-        let cell_idx = self.world.add_cell(cell, vector![pos.x, pos.y]);
-        let cell_bundle = CellBundle::new(meshes, color_materials, pos, cell.size(), cell_idx);
-        commands.spawn(cell_bundle);
+        let cell_idx = self.world_mut().add_cell(cell, vector![pos.x, pos.y]);
+        self.spawn_entity(cell_idx, commands, cell_mesh, palette);
+    }
+
+    fn spawn_entity(
+        &mut self,
+        cell_idx: usize,
+        commands: &mut Commands,
+        cell_mesh: &CellMesh,
+        palette: &Palette,
+    ) {
+        let world = self.world();
+        let Some(cell_wrapper) = world.cells[cell_idx].as_ref() else {
+            return;
+        };
+        let cell_id = cell_wrapper.id;
+        // The index may still hold the entity of a cell that died unseen
+        if let Some(Some((id, entity))) = self.entities.get(cell_idx) {
+            if *id == cell_id {
+                return;
+            }
+            commands.entity(*entity).despawn();
+        }
+        let pos = world.cell_position(cell_idx).unwrap();
+        let cell_bundle = CellBundle::new(
+            cell_mesh.0.clone(),
+            palette.default_material(),
+            Vec2::new(pos.x, pos.y),
+            cell_wrapper.inner.size(),
+            cell_idx,
+        );
+        let entity = commands.spawn(cell_bundle).id();
+        if self.entities.len() <= cell_idx {
+            self.entities.resize(cell_idx + 1, None);
+        }
+        self.entities[cell_idx] = Some((cell_id, entity));
+    }
+
+    /// Spawns and despawns entities for the cells in the world's birth and
+    /// death events, whether they are on screen or not. Each event is checked
+    /// against the cell now at its index, so seeing the same events twice is
+    /// harmless.
+    pub fn sync_entities(&mut self, commands: &mut Commands, cell_mesh: &CellMesh, palette: &Palette) {
+        let world = self.world();
+        let changed: Vec<usize> = world
+            .death_events
+            .iter()
+            .map(|event| event.index)
+            .chain(world.birth_events.iter().map(|event| event.index))
+            .collect();
+        changed.into_iter().for_each(|cell_idx| {
+            let id = self.world().cells[cell_idx].as_ref().map(|cell_wrapper| cell_wrapper.id);
+            let entity = self.entities.get(cell_idx).copied().flatten();
+            if entity.map(|(entity_id, _)| entity_id) == id {
+                return;
+            }
+            if let Some((_, entity)) = entity {
+                commands.entity(entity).despawn();
+                self.entities[cell_idx] = None;
+            }
+            if id.is_some() {
+                self.spawn_entity(cell_idx, commands, cell_mesh, palette);
+            }
+        });
     }
 }
 
-pub fn thousand_cells(
-    mut world_wrapper: ResMut<WorldWrapper>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut color_materials: ResMut<Assets<ColorMaterial>>,
-) {
-    for _ in 0..20000 {
-        let random_cell = Cell::new_random();
-        world_wrapper.add_cell(
+/// Fills the world from `CellSimSettings`: applies the scenario file and
+/// scatters the initial cells. Only the world is touched, see
+/// `spawn_cell_entities` for drawing them.
+pub fn populate(mut world_wrapper: ResMut<WorldWrapper>, settings: Res<CellSimSettings>) {
+    let world = world_wrapper.world_mut();
+    if let Some(path) = &settings.config_path {
+        match Scenario::load(path) {
+            Ok(scenario) => world.apply_scenario(&scenario),
+            Err(error) => error!("could not load {}: {}", path.display(), error),
+        }
+    }
+
+    let mut rng = match settings.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    for _ in 0..settings.initial_cells {
+        let random_cell = Cell::new_random_with(&mut rng);
+        world.add_cell(
             random_cell,
-            Vec2::new(
-                rand::random::<f32>() * 10000.,
-                rand::random::<f32>() * 1200.,
-            ),
-            &mut commands,
-            meshes.as_mut(),
-            color_materials.as_mut(),
+            vector![
+                rng.gen::<f32>() * settings.spawn_area.x,
+                rng.gen::<f32>() * settings.spawn_area.y
+            ],
         );
     }
+    world.refresh_queries();
 }
 
-pub fn update(
+/// Spawns an entity for every living cell that does not have one yet.
+pub fn spawn_cell_entities(
     mut commands: Commands,
     mut world_wrapper: ResMut<WorldWrapper>,
-    _time: Res<Time>,
-    mut cell_bundles: Query<(
-        Entity,
-        &CellId,
-        &ViewVisibility,
-        &mut Mesh2dHandle,
-        &mut Handle<ColorMaterial>,
-        &mut Transform,
-    )>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut _materials: ResMut<Assets<ColorMaterial>>,
+    cell_mesh: Res<CellMesh>,
+    palette: Res<Palette>,
 ) {
-    let start_time = std::time::Instant::now(); // For debug
-    world_wrapper.world.update();
+    (0..world_wrapper.world().cells.len()).for_each(|cell_idx| {
+        let spawned = matches!(world_wrapper.entities.get(cell_idx), Some(Some(_)));
+        if !spawned {
+            world_wrapper.spawn_entity(cell_idx, &mut commands, &cell_mesh, &palette);
+        }
+    });
+}
 
-    let world_update_time = start_time.elapsed(); // For debug
-    #[cfg(debug_assertions)]
-    {
-        world_wrapper.debug.frames += 1;
-        world_wrapper.debug.world_update_time += world_update_time;
-    }
+/// Advances the world on the main thread, see `SimTask` for the background
+/// alternative.
+pub fn step_world(
+    mut world_wrapper: ResMut<WorldWrapper>,
+    time: Res<Time>,
+    mut controls: ResMut<TimeControls>,
+) {
+    controls.advance(world_wrapper.world_mut(), time.delta_seconds());
+}
+
+/// Keeps cell entities in step with the world: spawns and despawns them from
+/// the world's events and moves and scales all of them, so cells coming into
+/// view are already in place. Only coloring skips the ones off screen.
+pub fn sync_cells(
+    mut commands: Commands,
+    mut world_wrapper: ResMut<WorldWrapper>,
+    cell_mesh: Res<CellMesh>,
+    palette: Res<Palette>,
+    mut cell_bundles: Query<(&CellId, &mut Transform)>,
+) {
+    world_wrapper.sync_entities(&mut commands, &cell_mesh, &palette);
+
+    let world = world_wrapper.world();
     cell_bundles
         .iter_mut()
-        .filter(|(_, _, visibility, _, _, _)| visibility.get())
         .for_each(
-            |(entity, cell_id, _visibiliy, mut mesh, mut _color, mut transform)| {
-                let start_time = std::time::Instant::now();
-
-                let cell_option = world_wrapper.world.cells.get(cell_id.cell_id).unwrap();
-                match cell_option {
-                    Some(cell) => {
-                        let rigid_body_handle = cell.rigid_body_handle;
-                        let rigid_body = world_wrapper
-                            .world
-                            .rigid_body_set
-                            .get(rigid_body_handle)
-                            .unwrap();
-
-                        let find_body_time = start_time.elapsed();
-
-                        // Mesh
-                        if cell.inner.size_changed {
-                            *mesh = meshes
-                                .add(shape::Circle::new(cell.inner.size()).into())
-                                .into();
-                        }
-
-                        let update_mesh_time = start_time.elapsed() - find_body_time;
-
-                        // Translation
-                        if rigid_body.is_moving() {
-                            let pos = rigid_body.position().translation.vector;
-                            transform.translation = Vec3::new(pos.x, pos.y, 0.);
-                        }
-
-                        #[cfg(debug_assertions)]
-                        {
-                            world_wrapper.debug.bevy_find_rigid_body_time += find_body_time;
-                            world_wrapper.debug.bevy_update_mesh_time += update_mesh_time;
-                            world_wrapper.debug.bevy_update_transform_time +=
-                                start_time.elapsed() - find_body_time - update_mesh_time;
-                        }
-                    },
-                    None => {
-                        commands.entity(entity).despawn();
-                    }
+            |(cell_id, mut transform)| {
+                // Dead cells are despawned by `sync_entities`
+                let Some(cell) = world.cells.get(cell_id.cell_id).unwrap() else {
+                    return;
+                };
+                let rigid_body_handle = cell.rigid_body_handle;
+                let rigid_body = world.rigid_body_set.get(rigid_body_handle).unwrap();
+
+                // Scale
+                let size = cell.inner.size();
+                if transform.scale.x != size {
+                    transform.scale = Vec3::new(size, size, 1.);
+                }
+
+                // Translation
+                let pos = rigid_body.position().translation.vector;
+                let translation = Vec3::new(pos.x, pos.y, 0.);
+                if transform.translation != translation {
+                    transform.translation = translation;
                 }
             },
         );
-
-    #[cfg(debug_assertions)]
-    {
-        world_wrapper.debug.bevy_update_time += start_time.elapsed() - world_update_time;
-        let debug_data = &world_wrapper.debug;
-        let world = &world_wrapper.world;
-        let total_per_frame =
-            (debug_data.world_update_time + debug_data.bevy_update_time) / debug_data.frames;
-        log::info!("world_wrapper::update times:\nTotal {:?}/f (est {:?} fps) \n\tworld update: {:?}/f\n\t\tcell update: {:?}/f, \n\t\tphysics_update: {:?}/f, \n\t\treplicate_cell_to_rapier: {:?}/f, \n\tbevy update: {:?}/f, \n\t\tfinding rigid body: {:?} \n\t\tupdating mesh: {:?} \n\t\tupdating transform: {:?}",
-                   total_per_frame,
-                   1000. / total_per_frame.as_millis() as f32,
-                   debug_data.world_update_time / debug_data.frames,
-                       world.cell_time / debug_data.frames,
-                       world.physics_time / debug_data.frames,
-                       world.replication_time / debug_data.frames,
-                   debug_data.bevy_update_time / debug_data.frames,
-                       debug_data.bevy_find_rigid_body_time / debug_data.frames,
-                       debug_data.bevy_update_mesh_time / debug_data.frames,
-                       debug_data.bevy_update_transform_time / debug_data.frames);
-    }
 }
 
 #[cfg(test)]
 mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use cell_sim::cell::component::{ComponentProps, COMPONENT_COUNT};
+
     use super::*;
 
     fn setup_app() -> App {
@@ -175,6 +246,14 @@ mod tests {
         ));
 
         app.insert_resource(WorldWrapper::default());
+        app.init_resource::<Palette>();
+        app.init_resource::<CellMesh>();
+        app.init_resource::<TimeControls>();
+        app.insert_resource(CellSimSettings {
+            initial_cells: 1000,
+            seed: Some(0),
+            ..default()
+        });
 
         app
     }
@@ -185,13 +264,95 @@ mod tests {
 
         app.insert_resource(WorldWrapper::default());
 
-        let thousand_cells = app.world.register_system(thousand_cells);
-        let updateid = app.world.register_system(update);
+        let populate = app.world.register_system(populate);
+        let spawn_cell_entities = app.world.register_system(spawn_cell_entities);
+        let step_world = app.world.register_system(step_world);
+        let sync_cells = app.world.register_system(sync_cells);
 
-        app.world.run_system(thousand_cells).unwrap();
+        app.world.run_system(populate).unwrap();
+        app.world.run_system(spawn_cell_entities).unwrap();
+        let count = app.world.query::<&CellId>().iter(&app.world).count();
+        assert_eq!(count, 1000);
 
         for _ in 0..10 {
-            app.world.run_system(updateid).unwrap();
+            app.world.run_system(step_world).unwrap();
+            app.world.run_system(sync_cells).unwrap();
         }
     }
+
+    #[test]
+    fn test_sync_entities() {
+        let mut app = setup_app();
+        let sync_cells = app.world.register_system(sync_cells);
+        let count = |app: &mut App| app.world.query::<&CellId>().iter(&app.world).count();
+
+        // Nothing is ever visible without a camera, like cells off-screen
+        let mut world_wrapper = app.world.resource_mut::<WorldWrapper>();
+        let world = world_wrapper.world_mut();
+        let mut cell = Cell::new_random();
+        cell.inner.chemicals.atp = 1000.;
+        cell.inner.nucleotides = world.cell_config.division_nucleotides * 2.;
+        cell.inner.proteins = world.cell_config.division_proteins * 4.;
+        cell.components = [Some(ComponentProps::default()); COMPONENT_COUNT];
+        // Added behind the wrapper's back, so only the daughter gets an entity
+        world.add_cell(cell, vector![100., 100.]);
+        world.update();
+        assert_eq!(world.birth_events.len(), 1);
+        let daughter = world.birth_events[0].index;
+        app.world.run_system(sync_cells).unwrap();
+        assert_eq!(count(&mut app), 1);
+
+        // Off-screen entities still follow their cell
+        let mut world_wrapper = app.world.resource_mut::<WorldWrapper>();
+        world_wrapper.world_mut().move_cell(daughter, vector![300., 300.]);
+        app.world.run_system(sync_cells).unwrap();
+        let transform = app
+            .world
+            .query_filtered::<&Transform, With<CellId>>()
+            .single(&app.world);
+        assert_eq!(transform.translation, Vec3::new(300., 300., 0.));
+
+        let mut world_wrapper = app.world.resource_mut::<WorldWrapper>();
+        world_wrapper.world_mut().remove_cells_within(vector![100., 100.], 1000.);
+        app.world.run_system(sync_cells).unwrap();
+        assert_eq!(count(&mut app), 0);
+    }
+
+    #[test]
+    fn test_reused_index() {
+        let mut app = setup_app();
+        let add_cell = |mut world_wrapper: ResMut<WorldWrapper>,
+                        mut commands: Commands,
+                        cell_mesh: Res<CellMesh>,
+                        palette: Res<Palette>| {
+            let cell = Cell::new_random();
+            world_wrapper.add_cell(cell, Vec2::new(100., 100.), &mut commands, &cell_mesh, &palette);
+        };
+        app.world.run_system_once(add_cell);
+
+        // The death is never synced before the index is reused
+        let mut world_wrapper = app.world.resource_mut::<WorldWrapper>();
+        let world = world_wrapper.world_mut();
+        world.refresh_queries();
+        assert_eq!(world.remove_cells_within(vector![100., 100.], 10.), 1);
+        world.update();
+        app.world.run_system_once(add_cell);
+
+        assert_eq!(app.world.query::<&CellId>().iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn test_seed() {
+        let genomes = || {
+            let mut app = setup_app();
+            app.world.run_system_once(populate);
+            let world = app.world.resource::<WorldWrapper>().world();
+            world
+                .cells
+                .iter()
+                .map(|cell_wrapper| cell_wrapper.as_ref().unwrap().inner.genome.fingerprint())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(genomes(), genomes());
+    }
 }